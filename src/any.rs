@@ -0,0 +1,304 @@
+//! Protocol agnostic access to the boot information, for kernels which can be booted by both
+//! stivale and stivale2 bootloaders.
+
+use core::ffi::CStr;
+
+use crate::common::MaskInfo;
+use crate::{v1, v2, Error};
+
+/// The protocol the kernel was booted with. There is no reliable way to tell the two structures
+/// apart at runtime, so this has to be derived from the header through which the kernel was
+/// loaded, for example by using a different entry point for each header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ProtocolHint {
+    /// The kernel was loaded through its `.stivalehdr` header.
+    V1,
+    /// The kernel was loaded through its `.stivale2hdr` header.
+    V2,
+}
+
+/// The boot information passed by either a stivale or a stivale2 bootloader.
+#[derive(Clone, Copy)]
+pub enum AnyBootInfo<'a> {
+    V1(&'a v1::StivaleStruct),
+    V2(&'a v2::StivaleStruct),
+}
+
+/// Returns the boot information at the provided address, interpreted according to `hint`. The
+/// structure is validated with [v1::StivaleStruct::load_with_validation] or
+/// [v2::StivaleStruct::load_with_validation].
+///
+/// ## Safety
+/// If the address passes the alignment checks, it must point to a structure of the protocol
+/// selected by `hint` which stays valid for the rest of the program.
+pub unsafe fn load_any(address: usize, hint: ProtocolHint) -> Result<AnyBootInfo<'static>, Error> {
+    match hint {
+        ProtocolHint::V1 => Ok(AnyBootInfo::V1(v1::StivaleStruct::load_with_validation(
+            address,
+        )?)),
+
+        ProtocolHint::V2 => Ok(AnyBootInfo::V2(v2::StivaleStruct::load_with_validation(
+            address,
+        )?)),
+    }
+}
+
+impl<'a> AnyBootInfo<'a> {
+    /// Returns the protocol the kernel was booted with.
+    pub fn protocol(&self) -> ProtocolHint {
+        match self {
+            AnyBootInfo::V1(_) => ProtocolHint::V1,
+            AnyBootInfo::V2(_) => ProtocolHint::V2,
+        }
+    }
+
+    /// Returns an iterator over the memory map entries, converted to stivale2 entries. Both
+    /// protocols use the same entry types. The iterator is empty if no memory map was passed.
+    pub fn memory_regions(&self) -> AnyMemoryRegionIter<'a> {
+        match *self {
+            AnyBootInfo::V1(stivale_struct) => {
+                AnyMemoryRegionIter::V1(stivale_struct.memory_map_iter())
+            }
+            AnyBootInfo::V2(stivale_struct) => {
+                AnyMemoryRegionIter::V2(stivale_struct.memory_map().map(|map| map.iter()))
+            }
+        }
+    }
+
+    /// Returns the command line passed to the kernel, if any. If the command line is not valid
+    /// UTF-8, only the valid part before the first invalid byte is returned.
+    pub fn cmdline(&self) -> Option<&'a str> {
+        let address = match *self {
+            AnyBootInfo::V1(stivale_struct) => stivale_struct.command_line,
+            AnyBootInfo::V2(stivale_struct) => stivale_struct.command_line()?.command_line,
+        };
+
+        let address = v2::utils::try_into_usize(address).filter(|&address| address != 0)?;
+        let bytes = unsafe { CStr::from_ptr(address as *const _) }.to_bytes();
+
+        Some(v2::utils::string_from_slice(bytes))
+    }
+
+    /// Returns the framebuffer set up by the bootloader, if any. The color masks of a stivale
+    /// framebuffer are zero unless [v1::StivaleStructV1Flags::EXTENDED_COLOUR_INFO] is set.
+    pub fn framebuffer(&self) -> Option<AnyFramebuffer> {
+        match *self {
+            AnyBootInfo::V1(stivale_struct) => {
+                if stivale_struct.framebuffer_addr == 0 {
+                    return None;
+                }
+
+                let extended = stivale_struct
+                    .flags_v1()
+                    .contains(v1::StivaleStructV1Flags::EXTENDED_COLOUR_INFO);
+                let mask = |mask: MaskInfo| if extended { mask } else { MaskInfo::default() };
+
+                Some(AnyFramebuffer {
+                    address: stivale_struct.framebuffer_addr,
+                    width: stivale_struct.framebuffer_width,
+                    height: stivale_struct.framebuffer_height,
+                    pitch: stivale_struct.framebuffer_pitch,
+                    bpp: stivale_struct.framebuffer_bpp,
+                    red_mask: mask(stivale_struct.red_mask()),
+                    green_mask: mask(stivale_struct.green_mask()),
+                    blue_mask: mask(stivale_struct.blue_mask()),
+                })
+            }
+
+            AnyBootInfo::V2(stivale_struct) => {
+                let framebuffer = stivale_struct.framebuffer()?;
+
+                Some(AnyFramebuffer {
+                    address: framebuffer.framebuffer_addr,
+                    width: framebuffer.framebuffer_width,
+                    height: framebuffer.framebuffer_height,
+                    pitch: framebuffer.framebuffer_pitch,
+                    bpp: framebuffer.framebuffer_bpp,
+                    red_mask: framebuffer.red_mask(),
+                    green_mask: framebuffer.green_mask(),
+                    blue_mask: framebuffer.blue_mask(),
+                })
+            }
+        }
+    }
+}
+
+/// The framebuffer passed by either protocol, as returned by [AnyBootInfo::framebuffer].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct AnyFramebuffer {
+    /// The address of the framebuffer.
+    pub address: u64,
+    /// The width of the framebuffer in pixels.
+    pub width: u16,
+    /// The height of the framebuffer in pixels.
+    pub height: u16,
+    /// The pitch of the framebuffer in bytes.
+    pub pitch: u16,
+    /// The number of bits per pixel.
+    pub bpp: u16,
+    pub red_mask: MaskInfo,
+    pub green_mask: MaskInfo,
+    pub blue_mask: MaskInfo,
+}
+
+/// Iterator over the memory map entries of either protocol. See [AnyBootInfo::memory_regions]
+/// for more information.
+#[derive(Clone)]
+pub enum AnyMemoryRegionIter<'a> {
+    V1(v1::StivaleMemoryMapIter<'a>),
+    V2(Option<v2::StivaleMemoryMapIter<'a>>),
+}
+
+impl Iterator for AnyMemoryRegionIter<'_> {
+    type Item = v2::StivaleMemoryMapEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            AnyMemoryRegionIter::V1(iter) => iter.next().map(|entry| {
+                v2::StivaleMemoryMapEntry::new(
+                    entry.base,
+                    entry.length,
+                    convert_entry_type(entry.entry_type()),
+                )
+            }),
+            AnyMemoryRegionIter::V2(iter) => iter.as_mut()?.next().copied(),
+        }
+    }
+}
+
+fn convert_entry_type(entry_type: v1::StivaleMemoryMapEntryType) -> v2::StivaleMemoryMapEntryType {
+    use v1::StivaleMemoryMapEntryType as V1;
+    use v2::StivaleMemoryMapEntryType as V2;
+
+    match entry_type {
+        V1::Usable => V2::Usable,
+        V1::Reserved => V2::Reserved,
+        V1::AcpiReclaimable => V2::AcpiReclaimable,
+        V1::AcpiNvs => V2::AcpiNvs,
+        V1::BadMemory => V2::BadMemory,
+        V1::BootloaderReclaimable => V2::BootloaderReclaimable,
+        V1::Kernel => V2::Kernel,
+        V1::Framebuffer => V2::Framebuffer,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::v2::writer::StructWriter;
+    use crate::v2::{tag_ids, StivaleMemoryMapEntry, StivaleMemoryMapEntryType};
+    use std::{boxed::Box, string::ToString, vec::Vec};
+
+    const CMDLINE: &[u8] = b"quiet\0";
+
+    #[repr(C, align(8))]
+    struct Buffer([u8; 512]);
+
+    fn v1_boot_info() -> AnyBootInfo<'static> {
+        let buffer = Box::leak(Box::new(Buffer([0; 512])));
+        let stivale_struct = unsafe { &mut *(buffer.0.as_mut_ptr() as *mut v1::StivaleStruct) };
+
+        stivale_struct.command_line = CMDLINE.as_ptr() as u64;
+        stivale_struct.framebuffer_addr = 0xfd000000;
+        stivale_struct.framebuffer_width = 640;
+        stivale_struct.framebuffer_bpp = 32;
+        stivale_struct.red_mask_size = 8;
+        stivale_struct.red_mask_shift = 16;
+
+        unsafe { load_any(buffer.0.as_ptr() as usize, ProtocolHint::V1) }.unwrap()
+    }
+
+    fn v2_boot_info() -> AnyBootInfo<'static> {
+        let buffer = Box::leak(Box::new(Buffer([0; 512])));
+        let mut writer = StructWriter::new(&mut buffer.0, "Limine", "2.0").unwrap();
+
+        writer
+            .push_raw_tag(
+                tag_ids::COMMAND_LINE,
+                &(CMDLINE.as_ptr() as u64).to_ne_bytes(),
+            )
+            .unwrap();
+        writer
+            .push_memory_map(&[StivaleMemoryMapEntry::new(
+                0x1000,
+                0x9f000,
+                StivaleMemoryMapEntryType::Usable,
+            )])
+            .unwrap();
+
+        let address = writer.struct_address() as usize;
+        unsafe { load_any(address, ProtocolHint::V2) }.unwrap()
+    }
+
+    #[test]
+    fn load_any_errors() {
+        assert_eq!(
+            unsafe { load_any(0, ProtocolHint::V1) }.err(),
+            Some(Error::LoadV1(v1::LoadError::NullAddress))
+        );
+        assert_eq!(
+            unsafe { load_any(0x1004, ProtocolHint::V1) }.err(),
+            Some(Error::LoadV1(v1::LoadError::Misaligned))
+        );
+        assert_eq!(
+            unsafe { load_any(0, ProtocolHint::V2) }.err(),
+            Some(Error::Load(v2::LoadError::NullAddress))
+        );
+        assert_eq!(
+            unsafe { load_any(0x1004, ProtocolHint::V2) }.err(),
+            Some(Error::Load(v2::LoadError::Misaligned))
+        );
+
+        // The messages name the protocol selected by the hint.
+        let message = unsafe { load_any(0, ProtocolHint::V1) }
+            .err()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            message,
+            "failed to load boot information: stivale structure address is null"
+        );
+    }
+
+    #[test]
+    fn common_surface() {
+        let v1 = v1_boot_info();
+        let v2 = v2_boot_info();
+
+        assert_eq!(v1.protocol(), ProtocolHint::V1);
+        assert_eq!(v2.protocol(), ProtocolHint::V2);
+
+        assert_eq!(v1.cmdline(), Some("quiet"));
+        assert_eq!(v2.cmdline(), Some("quiet"));
+
+        assert_eq!(v1.memory_regions().count(), 0);
+        let regions = v2
+            .memory_regions()
+            .map(|entry| (entry.base, entry.length, entry.entry_type()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            regions,
+            [(0x1000, 0x9f000, StivaleMemoryMapEntryType::Usable)]
+        );
+
+        // The masks of a stivale framebuffer are only used with extended colour information.
+        let framebuffer = v1.framebuffer().unwrap();
+        assert_eq!(framebuffer.width, 640);
+        assert_eq!(framebuffer.red_mask, MaskInfo::default());
+        assert!(v2.framebuffer().is_none());
+    }
+
+    #[test]
+    fn entry_type_conversion() {
+        assert_eq!(
+            convert_entry_type(v1::StivaleMemoryMapEntryType::Framebuffer) as u32,
+            v1::StivaleMemoryMapEntryType::Framebuffer as u32
+        );
+        assert_eq!(
+            convert_entry_type(v1::StivaleMemoryMapEntryType::BootloaderReclaimable),
+            StivaleMemoryMapEntryType::BootloaderReclaimable
+        );
+    }
+}
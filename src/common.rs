@@ -0,0 +1,381 @@
+//! Definitions shared between the stivale and stivale2 boot protocols.
+
+// The helpers are only used by the protocol modules.
+#![cfg_attr(not(any(feature = "v1", feature = "v2")), allow(dead_code))]
+
+use core::fmt;
+
+/// The size and shift of a color channel mask of a RGB framebuffer, as reported by both the
+/// stivale structure and the stivale2 framebuffer tag.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MaskInfo {
+    /// Size of the mask in bits.
+    pub size: u8,
+    /// Shift of the mask in bits.
+    pub shift: u8,
+}
+
+impl MaskInfo {
+    /// Shifts the provided color component into the position of this mask, so that the
+    /// components of all the channels can be OR'ed together to build a pixel value. The
+    /// component is not scaled to the size of the mask.
+    ///
+    /// If the shift is out of range for a `u32`, zero is returned.
+    pub fn apply(&self, color_component: u8) -> u32 {
+        (color_component as u32)
+            .checked_shl(self.shift as u32)
+            .unwrap_or(0)
+    }
+}
+
+/// A range of physical memory, given by its base address and its length in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MemoryRange {
+    /// Physical address of the start of the range.
+    pub base: u64,
+    /// Length of the range in bytes.
+    pub length: u64,
+}
+
+impl MemoryRange {
+    /// Creates a range of `length` bytes starting at `base`.
+    pub const fn new(base: u64, length: u64) -> Self {
+        Self { base, length }
+    }
+
+    /// Returns the address following the end of this range, saturated to `u64::MAX` if the
+    /// range extends to the top of the address space.
+    pub const fn end_address(&self) -> u64 {
+        self.base.saturating_add(self.length)
+    }
+
+    /// Returns the address following the end of this range, or `None` if it does not fit in a
+    /// `u64` because the range extends to the top of the address space.
+    pub const fn checked_end_address(&self) -> Option<u64> {
+        self.base.checked_add(self.length)
+    }
+
+    /// Returns true if the provided address lies within this range.
+    pub const fn contains(&self, address: u64) -> bool {
+        // Unlike the end address, the offset into the range cannot overflow.
+        address >= self.base && address - self.base < self.length
+    }
+
+    /// Returns the smallest range made of whole `page_size` pages covering this range, with the
+    /// base rounded down and the end rounded up to a multiple of `page_size`. The aligned range
+    /// may end exactly at the top of the address space; `None` is only returned if it would
+    /// extend beyond it. An empty range stays empty.
+    ///
+    /// ## Panics
+    /// Panics if `page_size` is zero.
+    pub fn aligned(&self, page_size: u64) -> Option<MemoryRange> {
+        assert!(page_size != 0, "page size must be non-zero");
+
+        let base = self.base / page_size * page_size;
+
+        // An empty range does not need any page.
+        if self.length == 0 {
+            return Some(MemoryRange::new(base, 0));
+        }
+
+        // The end address itself may not fit in a `u64`, so work from the length instead.
+        let length = self
+            .length
+            .checked_add(self.base - base)?
+            .checked_next_multiple_of(page_size)?;
+        base.checked_add(length - 1)?;
+
+        Some(MemoryRange::new(base, length))
+    }
+
+    /// Returns the number of `page_size` pages needed to map this range, which is the length of
+    /// [MemoryRange::aligned] in pages. Returns `None` if the aligned range does not fit in a
+    /// `u64`.
+    ///
+    /// ## Panics
+    /// Panics if `page_size` is zero.
+    pub fn page_count(&self, page_size: u64) -> Option<u64> {
+        self.aligned(page_size)
+            .map(|range| range.length / page_size)
+    }
+}
+
+/// Iterator over the start addresses of all the `frame_size` sized frames lying entirely within
+/// the provided ranges. The start of each range is rounded up and its end rounded down to a
+/// multiple of `frame_size`, so that no partial frame is yielded even if the ranges are not
+/// aligned to the frame size.
+#[derive(Clone)]
+pub(crate) struct FrameWalker<I> {
+    /// The ranges that are yet to be visited.
+    ranges: I,
+    /// The size of each frame in bytes.
+    frame_size: u64,
+    /// The start address of the frame that we are about to yield.
+    next_frame: u64,
+    /// The end address of the current range, rounded down to the frame size.
+    end: u64,
+}
+
+impl<I: Iterator<Item = MemoryRange>> FrameWalker<I> {
+    /// Creates a walker over the frames of the provided ranges.
+    ///
+    /// ## Panics
+    /// Panics if `frame_size` is zero.
+    pub(crate) fn new(ranges: I, frame_size: u64) -> Self {
+        assert!(frame_size != 0, "frame size must be non-zero");
+
+        Self {
+            ranges,
+            frame_size,
+            next_frame: 0x00,
+            end: 0x00,
+        }
+    }
+}
+
+impl<I: Iterator<Item = MemoryRange>> Iterator for FrameWalker<I> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.next_frame < self.end {
+                let frame = self.next_frame;
+                // Both addresses are multiples of the frame size, so this cannot overflow.
+                self.next_frame += self.frame_size;
+
+                return Some(frame);
+            }
+
+            let range = self.ranges.next()?;
+
+            // A range starting within the last partial frame of the address space has no frame.
+            let start = match range.base.checked_next_multiple_of(self.frame_size) {
+                Some(start) => start,
+                None => continue,
+            };
+
+            self.next_frame = start;
+            self.end = range.end_address() / self.frame_size * self.frame_size;
+        }
+    }
+}
+
+/// Converts an address passed by the bootloader to an `usize`, which fails if the address does
+/// not fit in a pointer on 32-bit targets.
+pub(crate) fn try_into_usize(address: u64) -> Option<usize> {
+    core::convert::TryFrom::try_from(address).ok()
+}
+
+/// Returns the bytes of a 0-terminated string stored in a slice, excluding the terminator.
+pub(crate) fn bytes_from_slice(slice: &[u8]) -> &[u8] {
+    let mut length = 0;
+
+    while length < slice.len() && slice[length] != 0 {
+        length += 1;
+    }
+
+    &slice[..length]
+}
+
+/// Validates and creates a string from a 0-terminated string stored in a slice. If the string
+/// is not valid UTF-8, only the valid part before the first invalid byte is returned.
+pub(crate) fn string_from_slice(slice: &[u8]) -> &str {
+    let bytes = bytes_from_slice(slice);
+
+    match core::str::from_utf8(bytes) {
+        Ok(string) => string,
+        // SAFETY: `valid_up_to` is the length of the longest valid UTF-8 prefix.
+        Err(error) => unsafe { core::str::from_utf8_unchecked(&bytes[..error.valid_up_to()]) },
+    }
+}
+
+/// Creates a string from a 0-terminated string stored in a slice. Unlike [string_from_slice],
+/// returns `None` if the slice is empty, if it does not contain the 0-terminator or if the
+/// string is not valid UTF-8.
+pub(crate) fn try_string_from_slice(slice: &[u8]) -> Option<&str> {
+    let length = slice.iter().position(|&byte| byte == 0)?;
+    core::str::from_utf8(&slice[..length]).ok()
+}
+
+/// Writes one row per memory map entry, given as `(base, length, raw entry type)`, formatted as
+/// `base..end  size  type`. Both protocols share the same entry type values.
+pub(crate) fn fmt_memory_map_table(
+    w: &mut dyn fmt::Write,
+    entries: impl Iterator<Item = (u64, u64, u32)>,
+) -> fmt::Result {
+    for (base, length, entry_type) in entries {
+        let (size, unit) = [(1 << 30, "GiB"), (1 << 20, "MiB"), (1 << 10, "KiB")]
+            .iter()
+            .find(|&&(unit, _)| length != 0 && length % unit == 0)
+            .map_or((length, "B"), |&(unit, name)| (length / unit, name));
+
+        writeln!(
+            w,
+            "{:#018x}..{:#018x}  {:>6} {:<3}  {}",
+            base,
+            base.saturating_add(length),
+            size,
+            unit,
+            memory_type_name(entry_type)
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Returns the short name of a raw memory map entry type.
+fn memory_type_name(entry_type: u32) -> &'static str {
+    match entry_type {
+        1 => "usable",
+        2 => "reserved",
+        3 => "acpi-reclaimable",
+        4 => "acpi-nvs",
+        5 => "bad",
+        0x1000 => "bootloader-reclaimable",
+        0x1001 => "kernel",
+        0x1002 => "framebuffer",
+        _ => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memory_map_table_units() {
+        extern crate std;
+
+        let mut table = std::string::String::new();
+        let entries = [(0x40000000, 0x80000000, 5), (0x500, 0x80, 0x4242)];
+        fmt_memory_map_table(&mut table, entries.iter().copied()).unwrap();
+
+        assert_eq!(
+            table,
+            "0x0000000040000000..0x00000000c0000000       2 GiB  bad\n\
+             0x0000000000000500..0x0000000000000580     128 B    unknown\n"
+        );
+    }
+
+    #[test]
+    fn string_from_slice_truncates() {
+        assert_eq!(string_from_slice(b""), "");
+        assert_eq!(string_from_slice(b"Limine\0\0"), "Limine");
+        assert_eq!(string_from_slice(b"Limine"), "Limine");
+        assert_eq!(string_from_slice(b"Lim\xffine\0"), "Lim");
+    }
+
+    #[test]
+    fn try_string_from_slice_rejects_malformed_input() {
+        assert_eq!(try_string_from_slice(b"Limine\0\0"), Some("Limine"));
+        assert_eq!(try_string_from_slice(b"\0"), Some(""));
+        // Bytes after the 0-terminator are not inspected.
+        assert_eq!(try_string_from_slice(b"2.0\0\xff"), Some("2.0"));
+
+        assert_eq!(try_string_from_slice(b""), None);
+        assert_eq!(try_string_from_slice(b"Limine"), None);
+        assert_eq!(try_string_from_slice(b"Lim\xffine\0"), None);
+        assert_eq!(try_string_from_slice(b"\xc3\0"), None);
+    }
+
+    #[test]
+    fn mask_apply() {
+        let red = MaskInfo { size: 8, shift: 16 };
+        let green = MaskInfo { size: 8, shift: 8 };
+        let blue = MaskInfo { size: 8, shift: 0 };
+
+        assert_eq!(
+            red.apply(0x12) | green.apply(0x34) | blue.apply(0x56),
+            0x123456
+        );
+        assert_eq!(
+            red.apply(255) | green.apply(255) | blue.apply(255),
+            0xffffff
+        );
+        assert_eq!(MaskInfo { size: 8, shift: 32 }.apply(255), 0);
+    }
+
+    #[test]
+    fn frame_walker() {
+        extern crate std;
+        use std::vec::Vec;
+
+        let ranges = [
+            // Not aligned, so only the frames at 0x2000 and 0x3000 are whole.
+            MemoryRange::new(0x1800, 0x2800),
+            // Smaller than a frame once aligned.
+            MemoryRange::new(0x10800, 0x1000),
+            MemoryRange::new(0x200000, 0x2000),
+            // The last frame is not whole, and yielding it would wrap the next frame address.
+            MemoryRange::new(u64::MAX - 0x1fff, 0x1fff),
+            MemoryRange::new(u64::MAX - 0x7ff, 0x800),
+        ];
+        let frames = FrameWalker::new(ranges.iter().copied(), 0x1000).collect::<Vec<_>>();
+
+        assert_eq!(
+            frames,
+            [0x2000, 0x3000, 0x200000, 0x201000, u64::MAX - 0x1fff]
+        );
+        assert_eq!(FrameWalker::new(ranges.iter().copied(), 0x2000).count(), 2);
+    }
+
+    #[test]
+    fn memory_range_aligned() {
+        // An unaligned ACPI region straddling three pages.
+        let range = MemoryRange::new(0x7fe1234, 0x2000);
+
+        assert_eq!(
+            range.aligned(0x1000),
+            Some(MemoryRange::new(0x7fe1000, 0x3000))
+        );
+        assert_eq!(range.page_count(0x1000), Some(3));
+        assert_eq!(
+            range.aligned(0x200000),
+            Some(MemoryRange::new(0x7e00000, 0x200000))
+        );
+        assert_eq!(range.page_count(0x200000), Some(1));
+
+        let aligned = MemoryRange::new(0x100000, 0x4000);
+        assert_eq!(aligned.aligned(0x1000), Some(aligned));
+        assert_eq!(MemoryRange::new(0x1234, 0).page_count(0x1000), Some(0));
+    }
+
+    #[test]
+    fn memory_range_at_the_top_of_memory() {
+        let range = MemoryRange::new(u64::MAX - 4096 + 1, 4096);
+
+        assert_eq!(range.checked_end_address(), None);
+        assert_eq!(range.end_address(), u64::MAX);
+        assert!(range.contains(u64::MAX));
+        assert!(!range.contains(u64::MAX - 4096));
+        assert!(!MemoryRange::new(0x1000, 0).contains(0x1000));
+
+        // The aligned range ends exactly at the top of the address space.
+        let range = MemoryRange::new(u64::MAX - 4096 + 1, 4096);
+        assert_eq!(range.aligned(0x1000), Some(range));
+        assert_eq!(range.page_count(0x1000), Some(1));
+
+        let range = MemoryRange::new(u64::MAX - 0x1233, 0x1000);
+        assert_eq!(
+            range.aligned(0x1000),
+            Some(MemoryRange::new(u64::MAX - 0x1fff, 0x2000))
+        );
+        assert_eq!(range.page_count(0x1000), Some(2));
+
+        // The range itself extends past the top of the address space.
+        let range = MemoryRange::new(u64::MAX - 0xfff, 0x1001);
+        assert_eq!(range.aligned(0x1000), None);
+        assert_eq!(range.page_count(0x1000), None);
+
+        // The rounded up length does not fit in a `u64`.
+        let range = MemoryRange::new(0x0, u64::MAX);
+        assert_eq!(range.aligned(0x1000), None);
+        assert_eq!(range.aligned(1), Some(range));
+
+        let range = MemoryRange::new(u64::MAX - 0x1fff, 0xfff);
+        assert_eq!(
+            range.aligned(0x1000),
+            Some(MemoryRange::new(u64::MAX - 0x1fff, 0x1000))
+        );
+    }
+}
@@ -0,0 +1,156 @@
+//! Error type shared by the fallible APIs of the crate.
+
+use core::fmt;
+
+#[cfg(feature = "v1")]
+use crate::v1;
+#[cfg(feature = "v2")]
+use crate::v2::header::HeaderParseError;
+#[cfg(feature = "v2")]
+use crate::v2::writer::StructWriterError;
+#[cfg(feature = "v2")]
+use crate::v2::{LoadError, ReclaimBufferTooSmall, UnsortedHolesError};
+
+/// Error returned by the fallible APIs spanning both boot protocols. The fallible APIs of the
+/// protocol modules return their own category error, which converts into this type so that
+/// callers can propagate all of them with `?`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The stivale2 boot information structure could not be loaded.
+    #[cfg(feature = "v2")]
+    Load(LoadError),
+    /// The stivale boot information structure could not be loaded.
+    #[cfg(feature = "v1")]
+    LoadV1(v1::LoadError),
+    /// A stivale2 header could not be parsed.
+    #[cfg(feature = "v2")]
+    HeaderParse(HeaderParseError),
+    /// A stivale2 structure could not be written.
+    #[cfg(feature = "v2")]
+    StructWriter(StructWriterError),
+    /// The holes to exclude from a stivale2 memory map are not sorted.
+    #[cfg(feature = "v2")]
+    MemoryMap(UnsortedHolesError),
+    /// The bootloader reclaimable regions of a stivale2 memory map could not be collected.
+    #[cfg(feature = "v2")]
+    Reclaim(ReclaimBufferTooSmall),
+}
+
+impl fmt::Display for Error {
+    // Without any protocol feature there are no variants to format.
+    #[cfg_attr(not(any(feature = "v1", feature = "v2")), allow(unused_variables))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            #[cfg(feature = "v2")]
+            Error::Load(error) => write!(f, "failed to load boot information: {}", error),
+            #[cfg(feature = "v1")]
+            Error::LoadV1(error) => write!(f, "failed to load boot information: {}", error),
+            #[cfg(feature = "v2")]
+            Error::HeaderParse(error) => write!(f, "failed to parse stivale2 header: {}", error),
+            #[cfg(feature = "v2")]
+            Error::StructWriter(error) => {
+                write!(f, "failed to write stivale2 structure: {}", error)
+            }
+            #[cfg(feature = "v2")]
+            Error::MemoryMap(error) => write!(f, "failed to query memory map: {}", error),
+            #[cfg(feature = "v2")]
+            Error::Reclaim(error) => write!(f, "failed to plan memory reclaim: {}", error),
+        }
+    }
+}
+
+#[cfg(feature = "v2")]
+impl From<LoadError> for Error {
+    fn from(error: LoadError) -> Self {
+        Error::Load(error)
+    }
+}
+
+#[cfg(feature = "v2")]
+impl From<HeaderParseError> for Error {
+    fn from(error: HeaderParseError) -> Self {
+        Error::HeaderParse(error)
+    }
+}
+
+#[cfg(feature = "v2")]
+impl From<StructWriterError> for Error {
+    fn from(error: StructWriterError) -> Self {
+        Error::StructWriter(error)
+    }
+}
+
+#[cfg(feature = "v1")]
+impl From<v1::LoadError> for Error {
+    fn from(error: v1::LoadError) -> Self {
+        Error::LoadV1(error)
+    }
+}
+
+#[cfg(feature = "v2")]
+impl From<UnsortedHolesError> for Error {
+    fn from(error: UnsortedHolesError) -> Self {
+        Error::MemoryMap(error)
+    }
+}
+
+#[cfg(feature = "v2")]
+impl From<ReclaimBufferTooSmall> for Error {
+    fn from(error: ReclaimBufferTooSmall) -> Self {
+        Error::Reclaim(error)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "v2")]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::string::ToString;
+
+    #[test]
+    fn display_messages() {
+        let errors = [
+            Error::from(LoadError::NullAddress),
+            LoadError::Misaligned.into(),
+            LoadError::UnterminatedBrand.into(),
+            LoadError::UnterminatedVersion.into(),
+            HeaderParseError::TooShort.into(),
+            HeaderParseError::UnmappedAddress(0x1000).into(),
+            StructWriterError::BufferTooSmall.into(),
+            StructWriterError::Misaligned.into(),
+            StructWriterError::StringTooLong.into(),
+            UnsortedHolesError { index: 2 }.into(),
+            ReclaimBufferTooSmall { required: 3 }.into(),
+        ];
+
+        for error in errors {
+            let message = error.to_string();
+            let (category, detail) = message.split_once(": ").unwrap();
+
+            assert!(category.starts_with("failed to "));
+            assert!(!detail.is_empty());
+        }
+
+        assert_eq!(
+            Error::from(LoadError::NullAddress).to_string(),
+            "failed to load boot information: stivale2 structure address is null"
+        );
+        assert_eq!(
+            Error::from(UnsortedHolesError { index: 2 }).to_string(),
+            "failed to query memory map: hole 2 starts before the end of the previous hole"
+        );
+        assert_eq!(
+            Error::from(ReclaimBufferTooSmall { required: 3 }).to_string(),
+            "failed to plan memory reclaim: buffer too small for 3 reclaimable regions"
+        );
+
+        #[cfg(feature = "v1")]
+        assert_eq!(
+            Error::from(v1::LoadError::Misaligned).to_string(),
+            "failed to load boot information: stivale structure address not 8-byte aligned"
+        );
+    }
+}
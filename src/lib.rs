@@ -1,7 +1,22 @@
 #![no_std]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 #[cfg(feature = "helper-macros")]
 pub use stivale_proc::*;
 
+#[cfg(all(feature = "v1", feature = "v2"))]
+mod any;
+pub mod common;
+mod error;
+#[cfg(all(test, any(feature = "v1", feature = "v2")))]
+mod test_utils;
+#[cfg(feature = "v1")]
 pub mod v1;
+#[cfg(feature = "v2")]
 pub mod v2;
+
+#[cfg(all(feature = "v1", feature = "v2"))]
+pub use any::*;
+pub use error::*;
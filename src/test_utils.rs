@@ -0,0 +1,113 @@
+//! Fixtures shared by the unit tests of the crate.
+
+extern crate std;
+
+/// Returns the raw bytes of `value`.
+///
+/// ## Safety
+/// `T` must not contain any padding bytes.
+pub(crate) unsafe fn bytes_of<T>(value: &T) -> &[u8] {
+    core::slice::from_raw_parts(value as *const T as *const u8, core::mem::size_of::<T>())
+}
+
+/// Returns a module string holding `name`, padded with null bytes.
+fn module_string(name: &[u8]) -> [u8; 128] {
+    let mut string = [0; 128];
+    string[..name.len()].copy_from_slice(name);
+    string
+}
+
+#[cfg(feature = "v1")]
+pub(crate) mod v1 {
+    use crate::v1::StivaleModule;
+
+    pub(crate) fn module(start: u64, end: u64, name: &[u8]) -> StivaleModule {
+        StivaleModule {
+            start,
+            end,
+            string: super::module_string(name),
+        }
+    }
+}
+
+#[cfg(feature = "v2")]
+pub(crate) mod v2 {
+    use super::std::{vec, vec::Vec};
+    use crate::v2::{
+        StivaleMemoryMapEntry, StivaleMemoryMapEntryType, StivaleMemoryMapTag, StivaleModule,
+        StivaleModuleTag, StivaleStruct,
+    };
+    use core::{mem, ptr};
+
+    /// Builds the memory of a tag made of a tag header, an entry count and a variable length
+    /// array of entries.
+    pub(crate) fn dst_tag<T>(identifier: u64, entries: &[T]) -> Vec<u64> {
+        assert!(mem::align_of::<T>() <= mem::align_of::<u64>());
+
+        let mut buf = vec![0u64; 3 + mem::size_of_val(entries).div_ceil(8)];
+        buf[0] = identifier;
+        buf[2] = entries.len() as u64;
+
+        unsafe {
+            ptr::copy_nonoverlapping(
+                entries.as_ptr(),
+                buf.as_mut_ptr().add(3) as *mut T,
+                entries.len(),
+            );
+        }
+
+        buf
+    }
+
+    /// Builds the zeroed memory of a tag whose entry count is stored in the word at
+    /// `count_index`, with room for `count` entries of `entry_words` words each after it.
+    pub(crate) fn raw_dst_tag(
+        identifier: u64,
+        count_index: usize,
+        count: usize,
+        entry_words: usize,
+    ) -> Vec<u64> {
+        let mut buf = vec![0u64; count_index + 1 + count * entry_words];
+        buf[0] = identifier;
+        buf[count_index] = count as u64;
+        buf
+    }
+
+    /// Chains the provided tags together in order, returning a structure pointing to the first.
+    pub(crate) fn chain(tags: &mut [Vec<u64>]) -> StivaleStruct {
+        let mut stivale_struct = StivaleStruct::new();
+
+        for tag in tags.iter_mut().rev() {
+            tag[1] = stivale_struct.tags;
+            stivale_struct.tags = tag.as_ptr() as u64;
+        }
+
+        stivale_struct
+    }
+
+    pub(crate) fn entry(
+        base: u64,
+        length: u64,
+        entry_type: StivaleMemoryMapEntryType,
+    ) -> StivaleMemoryMapEntry {
+        StivaleMemoryMapEntry::new(base, length, entry_type)
+    }
+
+    pub(crate) fn module(start: u64, end: u64, name: &[u8]) -> StivaleModule {
+        StivaleModule {
+            start,
+            end,
+            string: super::module_string(name),
+        }
+    }
+
+    /// Views a buffer built by [`dst_tag`] as a memory map tag.
+    pub(crate) fn memory_map(buf: &mut [u64]) -> &StivaleMemoryMapTag {
+        unsafe { &*StivaleMemoryMapTag::new_from_ptr_count(buf.as_mut_ptr() as *mut (), buf[2]) }
+    }
+
+    /// Views a buffer built by [`dst_tag`] as a modules tag.
+    pub(crate) fn modules(buf: &mut [u64]) -> &StivaleModuleTag {
+        unsafe { &*StivaleModuleTag::new_from_ptr_count(buf.as_mut_ptr() as *mut (), buf[2]) }
+    }
+}
@@ -4,11 +4,21 @@
 
 mod utils;
 
+use core::iter::FilterMap;
 use core::marker::PhantomData;
 
+use crate::common::FrameWalker;
+pub use crate::common::{MaskInfo, MemoryRange};
+
+/// The entry point field of the header, which is either a function pointer or zero.
+///
+/// Every variant is pointer sized and fully initialises the union, so the header never contains
+/// uninitialised bytes. Writing a union field is safe, so the union is only read through
+/// `unsafe` code where a variant is reinterpreted.
+#[cfg_attr(feature = "extra-traits", derive(Clone, Copy))]
 union StivaleHeaderEntryPoint {
     func: extern "C" fn(&'static StivaleStruct) -> !,
-    zero: u16,
+    zero: usize,
 }
 
 bitflags::bitflags! {
@@ -42,51 +52,62 @@ bitflags::bitflags! {
 }
 
 #[repr(C)]
+#[cfg_attr(feature = "extra-traits", derive(Clone, Copy))]
 pub struct StivaleHeader {
     stack: *const u8,
+    // The protocol uses 64-bit fields for pointers, even for 32-bit kernels.
+    #[cfg(target_pointer_width = "32")]
+    _stack_padding: u32,
     flags: StivaleHeaderFlags,
     framebuffer_width: u16,
     framebuffer_height: u16,
     framebuffer_bpp: u16,
     entry_point: StivaleHeaderEntryPoint,
+    #[cfg(target_pointer_width = "32")]
+    _entry_point_padding: u32,
 }
 
 impl StivaleHeader {
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
         Self {
             stack: core::ptr::null(),
+            #[cfg(target_pointer_width = "32")]
+            _stack_padding: 0x00,
             flags: StivaleHeaderFlags::empty(),
             framebuffer_width: 0x00,
             framebuffer_height: 0x00,
             framebuffer_bpp: 0x00,
+            // A zeroed entry point tells the bootloader to use the entry point of the kernel ELF.
             entry_point: StivaleHeaderEntryPoint { zero: 0x00 },
+            #[cfg(target_pointer_width = "32")]
+            _entry_point_padding: 0x00,
         }
     }
 
     /// Sets the requested framebuffer width. Only parsed if a graphics mode is requested. If
     /// set to zero, the bootloader would pick the best possible video mode automatically (recommended).
-    pub fn framebuffer_width(mut self, framebuffer_width: u16) -> Self {
+    pub const fn framebuffer_width(mut self, framebuffer_width: u16) -> Self {
         self.framebuffer_width = framebuffer_width;
         self
     }
 
     /// Sets the requested framebuffer height. Only parsed if a graphics mode is requested. If
     /// set to zero, the bootloader would pick the best possible video mode automatically (recommended).
-    pub fn framebuffer_height(mut self, framebuffer_height: u16) -> Self {
+    pub const fn framebuffer_height(mut self, framebuffer_height: u16) -> Self {
         self.framebuffer_height = framebuffer_height;
         self
     }
 
     /// Sets the requested framebuffer bpp. Only parsed if a graphics mode is requested. If
     /// set to zero, the bootloader would pick the best possible video mode automatically (recommended).
-    pub fn framebuffer_bpp(mut self, framebuffer_bpp: u16) -> Self {
+    pub const fn framebuffer_bpp(mut self, framebuffer_bpp: u16) -> Self {
         self.framebuffer_bpp = framebuffer_bpp;
         self
     }
 
     /// Sets the provided stivale header flags. See the documentation of [StivaleHeaderFlags]
     /// for more information.
-    pub fn flags(mut self, flags: StivaleHeaderFlags) -> Self {
+    pub const fn flags(mut self, flags: StivaleHeaderFlags) -> Self {
         self.flags = flags;
         self
     }
@@ -95,22 +116,61 @@ impl StivaleHeader {
     /// It can only be set to NULL for 64-bit kernels. 32-bit kernels are mandated to
     /// provide a vaild stack. 64-bit and 32-bit valid stacks must be at least 256 bytes
     /// in usable space and must be 16 byte aligned addresses.
-    pub fn stack(mut self, stack: *const u8) -> Self {
+    pub const fn stack(mut self, stack: *const u8) -> Self {
         self.stack = stack;
         self
     }
 
+    /// Returns `false` if no stack has been set in this header, which is only allowed for 64-bit
+    /// kernels. See [StivaleHeader::stack] for more information.
+    ///
+    /// Unlike the stivale2 `v2::StivaleHeader::validate_for_32bit`, an entry point alone is not
+    /// enough, as the stivale protocol mandates a stack for 32-bit kernels. The check can be done
+    /// at compile time on a `const` header, since the header builder is `const`.
+    pub const fn validate_for_32bit(&self) -> bool {
+        !self.stack.is_null()
+    }
+
     /// Sets the entry point address. If not zero, the bootloader would jump to the specified
     /// entry point instead of jumping to the entry point specified the kernel ELF.
-    pub fn entry_point(mut self, func: extern "C" fn(&'static StivaleStruct) -> !) -> Self {
+    pub const fn entry_point(mut self, func: extern "C" fn(&'static StivaleStruct) -> !) -> Self {
+        // Overwriting the whole union is safe, and `func` has the same size as `zero`.
         self.entry_point = StivaleHeaderEntryPoint { func };
         self
     }
 }
 
+impl Default for StivaleHeader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "extra-traits")]
+impl core::fmt::Debug for StivaleHeader {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // SAFETY: every variant of the union is pointer sized.
+        let entry_point = unsafe { self.entry_point.zero };
+
+        f.debug_struct("StivaleHeader")
+            .field("stack", &self.stack)
+            .field("flags", &self.flags)
+            .field("framebuffer_width", &self.framebuffer_width)
+            .field("framebuffer_height", &self.framebuffer_height)
+            .field("framebuffer_bpp", &self.framebuffer_bpp)
+            .field("entry_point", &(entry_point as *const ()))
+            .finish()
+    }
+}
+
 /// Structure representing a module, containing the information of a module that
 /// the bootloader loaded alongside the kernel.
+///
+/// Modules are ordered by their start address, then by their end address and then by their
+/// string, so that a slice of modules can be sorted by load address.
 #[repr(C)]
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "extra-traits", derive(Clone, Copy, Debug, Hash))]
 pub struct StivaleModule {
     /// Address where this module has been loaded.
     pub start: u64,
@@ -128,12 +188,26 @@ impl StivaleModule {
         self.end - self.start
     }
 
+    /// Returns true if the provided address lies within this module. The start address is
+    /// inclusive and the end address is exclusive.
+    #[inline]
+    pub fn contains_address(&self, address: u64) -> bool {
+        address >= self.start && address < self.end
+    }
+
     /// Returns the ASCII 0-terminated string passed to the module as specified in the config file
     /// as a rust string.
     #[inline]
     pub fn as_str(&self) -> &str {
         self::utils::string_from_slice(&self.string)
     }
+
+    /// Returns the module string, or `None` if it is not 0-terminated or not valid UTF-8. Unlike
+    /// [StivaleModule::as_str], a malformed string is never truncated.
+    #[inline]
+    pub fn try_as_str(&self) -> Option<&str> {
+        self::utils::try_string_from_slice(&self.string)
+    }
 }
 
 /// Iterator over all the modules that were loaded.
@@ -146,18 +220,21 @@ pub struct StivaleModuleIter<'a> {
     phantom: PhantomData<&'a StivaleModule>,
 }
 
+impl<'a> StivaleModuleIter<'a> {
+    /// Returns the next entry without advancing the iterator.
+    pub fn peek(&self) -> Option<&'a StivaleModule> {
+        self.sref.modules_as_slice().get(self.current as usize)
+    }
+}
+
 impl<'a> Iterator for StivaleModuleIter<'a> {
     type Item = &'a StivaleModule;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.current < self.sref.module_len {
-            let entry = &self.sref.modules_as_slice()[self.current as usize];
-            self.current += 1;
+        let entry = self.peek()?;
+        self.current += 1;
 
-            Some(entry)
-        } else {
-            None
-        }
+        Some(entry)
     }
 }
 
@@ -170,6 +247,7 @@ impl<'a> Iterator for StivaleModuleIter<'a> {
 /// any other entry.
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "extra-traits", derive(Eq, Hash))]
 pub enum StivaleMemoryMapEntryType {
     /// Usable memory.
     Usable = 1,
@@ -191,6 +269,7 @@ pub enum StivaleMemoryMapEntryType {
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "extra-traits", derive(PartialEq, Eq, Hash))]
 pub struct StivaleMemoryMapEntry {
     /// Physical address of base of the memory section.
     pub base: u64,
@@ -203,10 +282,44 @@ pub struct StivaleMemoryMapEntry {
 }
 
 impl StivaleMemoryMapEntry {
-    /// Returns the end address of this memory region.
+    /// Returns the end address of this memory region, saturated to `u64::MAX` if the region
+    /// extends to the top of the address space.
     #[inline]
     pub fn end_address(&self) -> u64 {
-        self.base + self.length
+        self.range().end_address()
+    }
+
+    /// Returns the end address of this memory region, or `None` if it does not fit in a `u64`
+    /// because the region extends to the top of the address space.
+    #[inline]
+    pub fn checked_end_address(&self) -> Option<u64> {
+        self.range().checked_end_address()
+    }
+
+    /// Returns the range of physical memory covered by this memory region.
+    #[inline]
+    pub fn range(&self) -> MemoryRange {
+        MemoryRange::new(self.base, self.length)
+    }
+
+    /// Returns the smallest range made of whole `page_size` pages covering this memory region,
+    /// which is needed to map regions that are not page aligned, such as ACPI tables. Returns
+    /// `None` if the aligned range extends beyond the top of the address space. See
+    /// [MemoryRange::aligned].
+    ///
+    /// ## Panics
+    /// Panics if `page_size` is zero.
+    pub fn aligned_range(&self, page_size: u64) -> Option<MemoryRange> {
+        self.range().aligned(page_size)
+    }
+
+    /// Returns the number of `page_size` pages needed to map this memory region. See
+    /// [MemoryRange::page_count].
+    ///
+    /// ## Panics
+    /// Panics if `page_size` is zero.
+    pub fn page_count(&self, page_size: u64) -> Option<u64> {
+        self.range().page_count(page_size)
     }
 
     /// Returns the entry type of this memory region. External function is required
@@ -215,6 +328,34 @@ impl StivaleMemoryMapEntry {
     pub fn entry_type(&self) -> StivaleMemoryMapEntryType {
         self.entry_type
     }
+
+    /// Returns true if the provided address lies within this memory region. The base address
+    /// is inclusive and the end address is exclusive.
+    #[inline]
+    pub fn contains(&self, address: u64) -> bool {
+        self.range().contains(address)
+    }
+}
+
+/// Per-type breakdown of the memory described by the memory map, in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct StivaleMemoryStats {
+    /// Total length of the usable entries.
+    pub usable: u64,
+    /// Total length of the reserved entries.
+    pub reserved: u64,
+    /// Total length of the ACPI reclaimable entries.
+    pub acpi_reclaimable: u64,
+    /// Total length of the ACPI NVS entries.
+    pub acpi_nvs: u64,
+    /// Total length of the bad memory entries.
+    pub bad_memory: u64,
+    /// Total length of the bootloader reclaimable entries.
+    pub bootloader_reclaimable: u64,
+    /// Total length of the entries containing the kernel and modules.
+    pub kernel: u64,
+    /// Total length of the framebuffer entries.
+    pub framebuffer: u64,
 }
 
 /// Iterator over all the memory regions provided by the stivale bootloader.
@@ -227,18 +368,59 @@ pub struct StivaleMemoryMapIter<'a> {
     phantom: PhantomData<&'a StivaleMemoryMapEntry>,
 }
 
+impl<'a> StivaleMemoryMapIter<'a> {
+    /// Returns the next entry without advancing the iterator.
+    pub fn peek(&self) -> Option<&'a StivaleMemoryMapEntry> {
+        self.sref.memory_map_as_slice().get(self.current as usize)
+    }
+}
+
 impl<'a> Iterator for StivaleMemoryMapIter<'a> {
     type Item = &'a StivaleMemoryMapEntry;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.current < self.sref.memory_map_len {
-            let entry = &self.sref.memory_map_as_slice()[self.current as usize];
-            self.current += 1;
+        let entry = self.peek()?;
+        self.current += 1;
 
-            Some(entry)
-        } else {
-            None
-        }
+        Some(entry)
+    }
+}
+
+/// Iterator over the start addresses of all the physical frames lying within the usable memory
+/// regions provided by the stivale bootloader.
+#[derive(Clone)]
+pub struct StivaleUsableFrameIter<'a> {
+    frames: FrameWalker<UsableRanges<'a>>,
+}
+
+/// The ranges of the usable memory map entries.
+type UsableRanges<'a> =
+    FilterMap<StivaleMemoryMapIter<'a>, fn(&'a StivaleMemoryMapEntry) -> Option<MemoryRange>>;
+
+/// Returns the range of the provided entry if it is usable.
+fn usable_range(entry: &StivaleMemoryMapEntry) -> Option<MemoryRange> {
+    (entry.entry_type() == StivaleMemoryMapEntryType::Usable).then(|| entry.range())
+}
+
+impl<'a> Iterator for StivaleUsableFrameIter<'a> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Usable entries are guaranteed to be 4096 byte aligned, but the requested frame size may
+        // be larger (and the guarantee is not worth trusting blindly), so the walker rounds them.
+        self.frames.next()
+    }
+}
+
+bitflags::bitflags! {
+    /// Bitfield representing the flags of the stivale structure, passed by the bootloader.
+    pub struct StivaleStructV1Flags: u64 {
+        /// The kernel was booted in a legacy BIOS mode. If unset, the kernel was booted in
+        /// UEFI mode.
+        const BIOS = 1 << 0;
+        /// Extended colour information (the RGB mask sizes and shifts) was passed by the
+        /// bootloader.
+        const EXTENDED_COLOUR_INFO = 1 << 1;
     }
 }
 
@@ -246,8 +428,9 @@ impl<'a> Iterator for StivaleMemoryMapIter<'a> {
 pub struct StivaleStruct {
     /// Address of the null-terminated command line.
     pub command_line: u64,
-    /// Pointer to the memory map array.
-    pub memory_map_array: [StivaleMemoryMapEntry; 0],
+    /// Address of the memory map array. Prefer [StivaleStruct::memory_map_as_slice] over
+    /// reading this field directly.
+    pub memory_map_addr: u64,
     /// Length of the memory map entries.
     pub memory_map_len: u64,
 
@@ -262,19 +445,31 @@ pub struct StivaleStruct {
     /// The framebuffer bits per pixels.
     pub framebuffer_bpp: u16,
 
-    /// Address of the RSDP ACPI structure.
+    /// Address of the RSDP ACPI structure. Prefer [StivaleStruct::rsdp_ptr] over reading this
+    /// field directly.
+    ///
+    /// ## Naming
+    /// The field name is misspelled (`adddres` instead of `address`). It is kept as-is since
+    /// renaming a public field of this `#[repr(C)]` structure would break existing users.
     pub rsdp_adddres: u64,
 
     /// The length of modules that the stivale bootloader loaded according to the
     /// config.
     pub module_len: u64,
-    /// Pointer to the modules array.
-    pub modules: [StivaleModule; 0],
+    /// Address of the modules array. Prefer [StivaleStruct::modules_as_slice] over reading
+    /// this field directly.
+    pub modules: u64,
 
-    /// UNIX epoch at boot, which is read from system RTC.
+    /// UNIX epoch at boot, which is read from system RTC. Prefer [StivaleStruct::unix_epoch]
+    /// over reading this field directly.
     pub unix_epoch: u64,
+    /// Raw flags passed by the bootloader. Prefer [StivaleStruct::flags_v1] over reading this
+    /// field directly.
     pub flags: u64,
 
+    /// The memory model of the framebuffer. Only meaningful if
+    /// [StivaleStructV1Flags::EXTENDED_COLOUR_INFO] is set.
+    pub framebuffer_memory_model: u8,
     /// Size of the red mask in RGB.
     pub red_mask_size: u8,
     /// Shift of the red mask in RGB.
@@ -296,36 +491,625 @@ pub struct StivaleStruct {
 }
 
 impl StivaleStruct {
-    /// Return's the modules array pointer as a rust slice.
+    /// Returns the structure at the provided address, after checking that the address is
+    /// non-null and 8-byte aligned.
+    ///
+    /// ## Safety
+    /// If the address passes these checks, it must point to a stivale structure which stays valid
+    /// for the rest of the program.
+    pub unsafe fn load_with_validation(address: usize) -> Result<&'static Self, LoadError> {
+        if address == 0 {
+            Err(LoadError::NullAddress)
+        } else if !address.is_multiple_of(core::mem::align_of::<u64>()) {
+            Err(LoadError::Misaligned)
+        } else {
+            Ok(&*(address as *const Self))
+        }
+    }
+
+    /// Returns a pointer to the RSDP ACPI structure, or `None` if the bootloader did not provide
+    /// its address or if the address does not fit in a pointer.
+    pub fn rsdp_ptr(&self) -> Option<*const u8> {
+        if self.rsdp_adddres == 0 {
+            None
+        } else {
+            utils::try_into_usize(self.rsdp_adddres).map(|address| address as *const u8)
+        }
+    }
+
+    /// Returns the width of the framebuffer in pixels as an `usize`.
+    #[inline]
+    pub fn framebuffer_width_usize(&self) -> usize {
+        self.framebuffer_width as usize
+    }
+
+    /// Returns the height of the framebuffer in pixels as an `usize`.
+    #[inline]
+    pub fn framebuffer_height_usize(&self) -> usize {
+        self.framebuffer_height as usize
+    }
+
+    /// Returns the pitch of the framebuffer in bytes as an `usize`.
+    #[inline]
+    pub fn framebuffer_pitch_usize(&self) -> usize {
+        self.framebuffer_pitch as usize
+    }
+
+    /// Returns the bits per pixel of the framebuffer as an `usize`.
+    #[inline]
+    pub fn framebuffer_bpp_usize(&self) -> usize {
+        self.framebuffer_bpp as usize
+    }
+
+    /// Returns the number of bytes per pixel of the framebuffer.
+    #[inline]
+    pub fn framebuffer_bytes_per_pixel(&self) -> usize {
+        self.framebuffer_bpp_usize() / 8
+    }
+
+    /// Returns the size and shift of the red mask. Only meaningful if
+    /// [StivaleStructV1Flags::EXTENDED_COLOUR_INFO] is set.
+    pub fn red_mask(&self) -> MaskInfo {
+        MaskInfo {
+            size: self.red_mask_size,
+            shift: self.red_mask_shift,
+        }
+    }
+
+    /// Returns the size and shift of the green mask. Only meaningful if
+    /// [StivaleStructV1Flags::EXTENDED_COLOUR_INFO] is set.
+    pub fn green_mask(&self) -> MaskInfo {
+        MaskInfo {
+            size: self.green_mask_size,
+            shift: self.green_mask_shift,
+        }
+    }
+
+    /// Returns the size and shift of the blue mask. Only meaningful if
+    /// [StivaleStructV1Flags::EXTENDED_COLOUR_INFO] is set.
+    pub fn blue_mask(&self) -> MaskInfo {
+        MaskInfo {
+            size: self.blue_mask_size,
+            shift: self.blue_mask_shift,
+        }
+    }
+
+    /// Returns the UNIX epoch at boot, or `None` if the epoch is zero which indicates that the
+    /// RTC was unavailable.
+    pub fn unix_epoch(&self) -> Option<u64> {
+        if self.unix_epoch == 0 {
+            None
+        } else {
+            Some(self.unix_epoch)
+        }
+    }
+
+    /// Returns the flags passed by the bootloader. Unknown bits are ignored.
+    pub fn flags_v1(&self) -> StivaleStructV1Flags {
+        StivaleStructV1Flags::from_bits_truncate(self.flags)
+    }
+
+    /// Return's the modules array pointer as a rust slice. The slice is empty if the address or
+    /// the length is zero, or if either does not fit in an `usize`.
     pub fn modules_as_slice(&self) -> &[StivaleModule] {
-        unsafe { core::slice::from_raw_parts(self.modules.as_ptr(), self.module_len as usize) }
+        unsafe { array_as_slice(self.modules, self.module_len) }
     }
 
     /// Returns an iterator over all the modules that were loaded.
-    pub fn modules_iter(&self) -> StivaleModuleIter {
+    pub fn modules_iter(&self) -> StivaleModuleIter<'_> {
         StivaleModuleIter {
             sref: self,
             current: 0,
-            phantom: PhantomData::default(),
+            phantom: PhantomData,
         }
     }
 
-    /// Return's memory map entries pointer as a rust slice.
+    /// Returns the first module which contains the provided address, if any.
+    pub fn module_by_address(&self, address: u64) -> Option<&StivaleModule> {
+        self.modules_iter()
+            .find(|module| module.contains_address(address))
+    }
+
+    /// Returns the module whose string, as specified in the config file, is exactly `name`. The
+    /// comparison is done on the raw bytes up to the 0-terminator, so the module string is not
+    /// required to be valid UTF-8.
+    pub fn module(&self, name: &str) -> Option<&StivaleModule> {
+        self.modules_iter()
+            .find(|module| utils::bytes_from_slice(&module.string) == name.as_bytes())
+    }
+
+    /// Returns the first module whose string, as specified in the config file, starts with
+    /// `prefix`. See [StivaleStruct::module] for more information.
+    pub fn module_with_prefix(&self, prefix: &str) -> Option<&StivaleModule> {
+        self.modules_iter()
+            .find(|module| utils::bytes_from_slice(&module.string).starts_with(prefix.as_bytes()))
+    }
+
+    /// Return's memory map entries pointer as a rust slice. The slice is empty if the address or
+    /// the length is zero, or if either does not fit in an `usize`.
     pub fn memory_map_as_slice(&self) -> &[StivaleMemoryMapEntry] {
-        unsafe {
-            core::slice::from_raw_parts(
-                self.memory_map_array.as_ptr(),
-                self.memory_map_len as usize,
-            )
-        }
+        unsafe { array_as_slice(self.memory_map_addr, self.memory_map_len) }
     }
 
     /// Returns an iterator over all the memory regions.
-    pub fn memory_map_iter(&self) -> StivaleMemoryMapIter {
+    pub fn memory_map_iter(&self) -> StivaleMemoryMapIter<'_> {
         StivaleMemoryMapIter {
             sref: self,
             current: 0x00,
-            phantom: PhantomData::default(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns an iterator over the start addresses of all the `frame_size` sized physical frames
+    /// lying within the usable memory regions. The start of each region is rounded up and the end
+    /// rounded down to a multiple of `frame_size`.
+    ///
+    /// ## Panics
+    /// Panics if `frame_size` is zero.
+    pub fn usable_frames(&self, frame_size: u64) -> StivaleUsableFrameIter<'_> {
+        StivaleUsableFrameIter {
+            frames: FrameWalker::new(self.memory_map_iter().filter_map(usable_range), frame_size),
+        }
+    }
+
+    /// Returns the memory map entry containing the provided address, if any. As the entries are
+    /// sorted by base address, the search stops at the first entry starting after the address.
+    pub fn memory_region_for(&self, address: u64) -> Option<&StivaleMemoryMapEntry> {
+        self.memory_map_iter()
+            .take_while(|entry| entry.base <= address)
+            .find(|entry| entry.contains(address))
+    }
+
+    /// Binary searches the memory map for an entry whose base address is `address`. Following the
+    /// convention of [slice::binary_search_by_key], returns `Ok(index)` of the matching entry, or
+    /// `Err(index)` of where an entry with that base address would be inserted. This relies on the
+    /// entries being sorted by base address.
+    pub fn memory_map_binary_search_by_address(&self, address: u64) -> Result<usize, usize> {
+        self.memory_map_as_slice()
+            .binary_search_by_key(&address, |entry| entry.base)
+    }
+
+    /// Returns the memory map entry containing the provided address, if any. This is an
+    /// `O(log n)` version of [StivaleStruct::memory_region_for].
+    pub fn memory_region_for_fast(&self, address: u64) -> Option<&StivaleMemoryMapEntry> {
+        let index = match self.memory_map_binary_search_by_address(address) {
+            Ok(index) => index,
+            // The entry before the insertion point is the only one which can contain the address.
+            Err(index) => index.checked_sub(1)?,
+        };
+
+        let entry = &self.memory_map_as_slice()[index];
+        entry.contains(address).then_some(entry)
+    }
+
+    /// Writes the memory map as a table with one `base..end  size  type` row per entry, without
+    /// allocating. Sizes are printed in the largest of GiB, MiB or KiB which divides them.
+    pub fn fmt_memory_map_table(&self, w: &mut dyn core::fmt::Write) -> core::fmt::Result {
+        crate::common::fmt_memory_map_table(
+            w,
+            self.memory_map_iter()
+                .map(|entry| (entry.base, entry.length, entry.entry_type() as u32)),
+        )
+    }
+
+    /// Returns the total length of all the memory map entries, regardless of their type. Returns
+    /// `None` if the sum overflows.
+    pub fn total_memory(&self) -> Option<u64> {
+        self.memory_map_iter()
+            .try_fold(0u64, |total, entry| total.checked_add(entry.length))
+    }
+
+    /// Returns the total length of the usable memory map entries. Returns `None` if the sum
+    /// overflows.
+    pub fn usable_memory(&self) -> Option<u64> {
+        self.memory_map_iter()
+            .filter(|entry| entry.entry_type() == StivaleMemoryMapEntryType::Usable)
+            .try_fold(0u64, |total, entry| total.checked_add(entry.length))
+    }
+
+    /// Returns the total length of the memory map entries broken down by their type. Returns
+    /// `None` if any of the sums overflows.
+    pub fn memory_stats(&self) -> Option<StivaleMemoryStats> {
+        let mut stats = StivaleMemoryStats::default();
+
+        for entry in self.memory_map_iter() {
+            let total = match entry.entry_type() {
+                StivaleMemoryMapEntryType::Usable => &mut stats.usable,
+                StivaleMemoryMapEntryType::Reserved => &mut stats.reserved,
+                StivaleMemoryMapEntryType::AcpiReclaimable => &mut stats.acpi_reclaimable,
+                StivaleMemoryMapEntryType::AcpiNvs => &mut stats.acpi_nvs,
+                StivaleMemoryMapEntryType::BadMemory => &mut stats.bad_memory,
+                StivaleMemoryMapEntryType::BootloaderReclaimable => {
+                    &mut stats.bootloader_reclaimable
+                }
+                StivaleMemoryMapEntryType::Kernel => &mut stats.kernel,
+                StivaleMemoryMapEntryType::Framebuffer => &mut stats.framebuffer,
+            };
+
+            *total = total.checked_add(entry.length)?;
+        }
+
+        Some(stats)
+    }
+}
+
+/// Error returned by [StivaleStruct::load_with_validation].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LoadError {
+    /// The address of the structure is zero.
+    NullAddress,
+    /// The address of the structure is not 8-byte aligned.
+    Misaligned,
+}
+
+impl core::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            LoadError::NullAddress => f.write_str("stivale structure address is null"),
+            LoadError::Misaligned => f.write_str("stivale structure address not 8-byte aligned"),
+        }
+    }
+}
+
+/// Returns the `len` elements at `address` as a slice, or an empty slice if the array was not
+/// passed by the bootloader.
+///
+/// ## Safety
+/// If both are non-zero, `address` must point to `len` valid elements which outlive `'a`.
+unsafe fn array_as_slice<'a, T>(address: u64, len: u64) -> &'a [T] {
+    match (utils::try_into_usize(address), utils::try_into_usize(len)) {
+        (Some(address), Some(len)) if address != 0 && len != 0 => {
+            core::slice::from_raw_parts(address as *const T, len)
+        }
+        _ => &[],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{bytes_of, v1::module};
+
+    extern "C" fn entry(_: &'static StivaleStruct) -> ! {
+        unreachable!()
+    }
+
+    #[test]
+    fn header_layout() {
+        assert_eq!(core::mem::size_of::<StivaleHeader>(), 24);
+    }
+
+    #[test]
+    fn default_header() {
+        let (default, new) = (StivaleHeader::default(), StivaleHeader::new());
+
+        assert_eq!(unsafe { bytes_of(&default) }, unsafe { bytes_of(&new) });
+        assert!(unsafe { bytes_of(&default) }.iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn struct_layout() {
+        use core::mem::offset_of;
+
+        assert_eq!(offset_of!(StivaleStruct, memory_map_addr), 8);
+        assert_eq!(offset_of!(StivaleStruct, memory_map_len), 16);
+        assert_eq!(offset_of!(StivaleStruct, framebuffer_addr), 24);
+        assert_eq!(offset_of!(StivaleStruct, rsdp_adddres), 40);
+        assert_eq!(offset_of!(StivaleStruct, module_len), 48);
+        assert_eq!(offset_of!(StivaleStruct, modules), 56);
+        assert_eq!(offset_of!(StivaleStruct, unix_epoch), 64);
+        assert_eq!(offset_of!(StivaleStruct, flags), 72);
+        assert_eq!(offset_of!(StivaleStruct, framebuffer_memory_model), 80);
+        assert_eq!(offset_of!(StivaleStruct, red_mask_size), 81);
+        assert_eq!(offset_of!(StivaleStruct, blue_mask_shift), 86);
+        assert_eq!(offset_of!(StivaleStruct, smbios_entry_32), 88);
+        assert_eq!(offset_of!(StivaleStruct, smbios_entry_64), 96);
+        assert_eq!(core::mem::size_of::<StivaleStruct>(), 104);
+    }
+
+    fn map_entry(
+        base: u64,
+        length: u64,
+        entry_type: StivaleMemoryMapEntryType,
+    ) -> StivaleMemoryMapEntry {
+        StivaleMemoryMapEntry {
+            base,
+            length,
+            entry_type,
+            padding: 0,
+        }
+    }
+
+    fn sample_memory_map() -> [StivaleMemoryMapEntry; 5] {
+        [
+            map_entry(0x0, 0x1000, StivaleMemoryMapEntryType::Reserved),
+            map_entry(0x1000, 0x9f000, StivaleMemoryMapEntryType::Usable),
+            map_entry(0x100000, 0x100000, StivaleMemoryMapEntryType::Kernel),
+            map_entry(0x200000, 0x400000, StivaleMemoryMapEntryType::Usable),
+            map_entry(
+                0x600000,
+                0x10000,
+                StivaleMemoryMapEntryType::AcpiReclaimable,
+            ),
+        ]
+    }
+
+    /// Returns a structure pointing to the provided memory map, which has to outlive it.
+    fn with_memory_map(entries: &[StivaleMemoryMapEntry]) -> StivaleStruct {
+        let mut stivale_struct: StivaleStruct = unsafe { core::mem::zeroed() };
+        stivale_struct.memory_map_addr = entries.as_ptr() as u64;
+        stivale_struct.memory_map_len = entries.len() as u64;
+        stivale_struct
+    }
+
+    #[test]
+    fn memory_map_through_pointer() {
+        let entries = sample_memory_map();
+        let stivale_struct = with_memory_map(&entries);
+
+        assert_eq!(stivale_struct.memory_map_as_slice().len(), 5);
+        assert_eq!(stivale_struct.memory_map_iter().count(), 5);
+        assert_eq!(
+            stivale_struct
+                .memory_map_iter()
+                .nth(3)
+                .map(|entry| entry.base),
+            Some(0x200000)
+        );
+
+        // A missing array is treated as empty, whatever the length says.
+        let mut stivale_struct = with_memory_map(&entries);
+        stivale_struct.memory_map_addr = 0;
+        assert!(stivale_struct.memory_map_as_slice().is_empty());
+        assert!(stivale_struct.memory_map_iter().next().is_none());
+    }
+
+    #[test]
+    fn usable_frames() {
+        let entries = [
+            map_entry(0x0, 0x1000, StivaleMemoryMapEntryType::Reserved),
+            // Not aligned to the frame size, so only the frames at 0x2000 and 0x3000 are whole.
+            map_entry(0x1800, 0x2800, StivaleMemoryMapEntryType::Usable),
+            map_entry(0x100000, 0x4000, StivaleMemoryMapEntryType::Kernel),
+            map_entry(0x200000, 0x4000, StivaleMemoryMapEntryType::Usable),
+        ];
+        let stivale_struct = with_memory_map(&entries);
+        let frames = stivale_struct.usable_frames(0x1000);
+
+        assert_eq!(frames.clone().count(), 6);
+        assert_eq!(frames.clone().next(), Some(0x2000));
+        assert_eq!(frames.clone().nth(2), Some(0x200000));
+        assert_eq!(frames.last(), Some(0x203000));
+
+        // Larger frames are rounded within each region as well.
+        let frames = stivale_struct.usable_frames(0x2000);
+        assert_eq!(frames.clone().count(), 3);
+        assert_eq!(frames.clone().next(), Some(0x2000));
+        assert_eq!(frames.last(), Some(0x202000));
+    }
+
+    #[test]
+    fn memory_region_for_boundaries() {
+        // Leave a gap between the usable and the ACPI entries.
+        let mut entries = sample_memory_map();
+        entries[4].base = 0x700000;
+        let stivale_struct = with_memory_map(&entries);
+
+        for lookup in [
+            StivaleStruct::memory_region_for,
+            StivaleStruct::memory_region_for_fast,
+        ] {
+            let base = |address| lookup(&stivale_struct, address).map(|entry| entry.base);
+
+            // The base address is inclusive.
+            assert_eq!(base(0x0), Some(0x0));
+            assert_eq!(base(0x1000), Some(0x1000));
+            assert_eq!(base(0x200000), Some(0x200000));
+            // The end address is exclusive.
+            assert_eq!(base(0xfff), Some(0x0));
+            assert_eq!(base(0x5fffff), Some(0x200000));
+            assert_eq!(base(0x600000), None);
+            // Addresses in the gap and past the last entry.
+            assert_eq!(base(0x6fffff), None);
+            assert_eq!(base(0x70ffff), Some(0x700000));
+            assert_eq!(base(0x710000), None);
+            // Between the end of the first usable entry and the kernel.
+            assert_eq!(base(0xa0000), None);
+        }
+    }
+
+    #[test]
+    fn module_lookup() {
+        let modules = [
+            module(0x200000, 0x204000, b"kernel.sym"),
+            module(0x204000, 0x300000, b"initrd"),
+            module(0x300000, 0x400000, b"initrd.old"),
+            module(0x400000, 0x401000, b"font\xff.psf"),
+        ];
+        let mut stivale_struct: StivaleStruct = unsafe { core::mem::zeroed() };
+        stivale_struct.modules = modules.as_ptr() as u64;
+        stivale_struct.module_len = modules.len() as u64;
+
+        let start = |module: Option<&StivaleModule>| module.map(|module| module.start);
+
+        // Exact matches are not fooled by other modules sharing the name as a prefix.
+        assert_eq!(start(stivale_struct.module("initrd")), Some(0x204000));
+        assert_eq!(start(stivale_struct.module("initrd.old")), Some(0x300000));
+        assert_eq!(start(stivale_struct.module("init")), None);
+        assert_eq!(start(stivale_struct.module("initrd.old.bak")), None);
+        assert_eq!(start(stivale_struct.module("")), None);
+
+        // The first module in load order wins.
+        assert_eq!(
+            start(stivale_struct.module_with_prefix("init")),
+            Some(0x204000)
+        );
+        assert_eq!(
+            start(stivale_struct.module_with_prefix("initrd.")),
+            Some(0x300000)
+        );
+        assert_eq!(start(stivale_struct.module_with_prefix("")), Some(0x200000));
+        assert_eq!(start(stivale_struct.module_with_prefix("vmlinuz")), None);
+
+        // Names which are not valid UTF-8 are compared on their raw bytes.
+        assert_eq!(
+            start(stivale_struct.module_with_prefix("font")),
+            Some(0x400000)
+        );
+        assert_eq!(start(stivale_struct.module("font")), None);
+        assert_eq!(start(stivale_struct.module("font.psf")), None);
+    }
+
+    #[test]
+    fn memory_totals() {
+        let entries = sample_memory_map();
+        let stivale_struct = with_memory_map(&entries);
+
+        // 0x1000 + 0x9f000 + 0x100000 + 0x400000 + 0x10000
+        assert_eq!(stivale_struct.total_memory(), Some(0x5b0000));
+        // 0x9f000 + 0x400000
+        assert_eq!(stivale_struct.usable_memory(), Some(0x49f000));
+        assert_eq!(
+            stivale_struct.memory_stats(),
+            Some(StivaleMemoryStats {
+                usable: 0x49f000,
+                reserved: 0x1000,
+                acpi_reclaimable: 0x10000,
+                kernel: 0x100000,
+                ..StivaleMemoryStats::default()
+            })
+        );
+    }
+
+    #[test]
+    fn memory_totals_overflow() {
+        // The total overflows, but neither of the per-type sums does.
+        let entries = [
+            map_entry(0x0, 1 << 63, StivaleMemoryMapEntryType::Usable),
+            map_entry(1 << 63, 1 << 63, StivaleMemoryMapEntryType::Reserved),
+        ];
+        let stivale_struct = with_memory_map(&entries);
+
+        assert_eq!(stivale_struct.total_memory(), None);
+        assert_eq!(stivale_struct.usable_memory(), Some(1 << 63));
+        assert_eq!(
+            stivale_struct.memory_stats(),
+            Some(StivaleMemoryStats {
+                usable: 1 << 63,
+                reserved: 1 << 63,
+                ..StivaleMemoryStats::default()
+            })
+        );
+
+        let entries = [
+            map_entry(0x0, 1 << 63, StivaleMemoryMapEntryType::Usable),
+            map_entry(1 << 63, 1 << 63, StivaleMemoryMapEntryType::Usable),
+        ];
+        let stivale_struct = with_memory_map(&entries);
+
+        assert_eq!(stivale_struct.usable_memory(), None);
+        assert_eq!(stivale_struct.memory_stats(), None);
+    }
+
+    #[test]
+    fn header_validate_for_32bit() {
+        static STACK: [u8; 4096] = [0; 4096];
+
+        assert!(!StivaleHeader::new().validate_for_32bit());
+        assert!(!StivaleHeader::new().entry_point(entry).validate_for_32bit());
+        assert!(StivaleHeader::new()
+            .stack(STACK.as_ptr())
+            .validate_for_32bit());
+
+        // An entry point does not make up for a missing stack, even in a constant context.
+        const HEADER: StivaleHeader = StivaleHeader::new().entry_point(entry);
+        const _: () = assert!(!HEADER.validate_for_32bit());
+        const _: () = assert!(HEADER.stack(STACK.as_ptr()).validate_for_32bit());
+    }
+
+    #[test]
+    #[cfg(feature = "extra-traits")]
+    fn extra_traits() {
+        extern crate std;
+        use std::format;
+
+        let header = StivaleHeader::new().framebuffer_bpp(32);
+        assert!(format!("{:?}", header.clone()).contains("framebuffer_bpp: 32"));
+
+        let module = StivaleModule {
+            start: 0x1000,
+            end: 0x2000,
+            string: [0; 128],
+        };
+        assert_eq!(module, module.clone());
+        assert!(
+            module
+                < StivaleModule {
+                    start: 0x1000,
+                    end: 0x3000,
+                    ..module
+                }
+        );
+    }
+
+    #[test]
+    fn color_masks() {
+        let mut stivale_struct: StivaleStruct = unsafe { core::mem::zeroed() };
+        stivale_struct.red_mask_size = 8;
+        stivale_struct.red_mask_shift = 16;
+        stivale_struct.green_mask_size = 8;
+        stivale_struct.green_mask_shift = 8;
+        stivale_struct.blue_mask_size = 8;
+        stivale_struct.blue_mask_shift = 0;
+
+        assert_eq!(stivale_struct.red_mask(), MaskInfo { size: 8, shift: 16 });
+
+        let pixel = stivale_struct.red_mask().apply(0xaa)
+            | stivale_struct.green_mask().apply(0xbb)
+            | stivale_struct.blue_mask().apply(0xcc);
+        assert_eq!(pixel, 0xaabbcc);
+    }
+
+    #[test]
+    fn memory_map_entry_at_the_top_of_memory() {
+        let entry = StivaleMemoryMapEntry {
+            base: u64::MAX - 4096 + 1,
+            length: 4096,
+            entry_type: StivaleMemoryMapEntryType::Reserved,
+            padding: 0,
+        };
+
+        assert_eq!(entry.checked_end_address(), None);
+        assert_eq!(entry.end_address(), u64::MAX);
+        assert!(entry.contains(u64::MAX));
+        assert!(!entry.contains(u64::MAX - 4096));
+    }
+
+    #[test]
+    fn memory_map_entry_aligned_range() {
+        let entry = StivaleMemoryMapEntry {
+            base: 0x9fc00,
+            length: 0x400,
+            entry_type: StivaleMemoryMapEntryType::AcpiReclaimable,
+            padding: 0,
+        };
+
+        assert_eq!(
+            entry.aligned_range(0x1000),
+            Some(MemoryRange::new(0x9f000, 0x1000))
+        );
+        assert_eq!(entry.page_count(0x1000), Some(1));
+    }
+
+    #[test]
+    fn try_into_usize() {
+        assert_eq!(utils::try_into_usize(0xe0000), Some(0xe0000));
+
+        if cfg!(target_pointer_width = "32") {
+            assert_eq!(utils::try_into_usize(1 << 32), None);
+        } else {
+            assert_eq!(utils::try_into_usize(1 << 32), Some(1 << 32));
         }
     }
 }
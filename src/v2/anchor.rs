@@ -0,0 +1,187 @@
+/// The magic bytes at the start of a [StivaleAnchor].
+pub const STIVALE2_ANCHOR_MAGIC: [u8; 15] = *b"STIVALE2 ANCHOR";
+
+/// The anchor lets the bootloader find the stivale2 header of a kernel which is not an ELF
+/// file, for example a flat binary.
+///
+/// The anchor has to be placed in the kernel image on a 16-byte boundary, so it should be put
+/// in its own section which is kept by the linker script and aligned to 16 bytes:
+///
+/// ```rust,no_run
+/// use stivale_boot::v2::StivaleAnchor;
+///
+/// #[link_section = ".stivale2anchor"]
+/// #[used]
+/// static STIVALE_ANCHOR: StivaleAnchor = StivaleAnchor::new()
+///     .phys_load_addr(0x100000)
+///     .phys_stivale2hdr(0x100040);
+/// ```
+#[repr(C, align(16))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StivaleAnchor {
+    anchor: [u8; 15],
+    bits: u8,
+    phys_load_addr: u64,
+    phys_bss_start: u64,
+    phys_bss_end: u64,
+    phys_stivale2hdr: u64,
+}
+
+impl StivaleAnchor {
+    /// Creates a new anchor for a 64-bit kernel, with all the addresses set to zero.
+    pub const fn new() -> Self {
+        Self {
+            anchor: STIVALE2_ANCHOR_MAGIC,
+            bits: 64,
+            phys_load_addr: 0,
+            phys_bss_start: 0,
+            phys_bss_end: 0,
+            phys_stivale2hdr: 0,
+        }
+    }
+
+    /// Sets the bitness of the kernel, which is either 32 or 64.
+    pub const fn bits(mut self, bits: u8) -> Self {
+        self.bits = bits;
+        self
+    }
+
+    /// Sets the physical address at which the kernel image has to be loaded.
+    pub const fn phys_load_addr(mut self, phys_load_addr: u64) -> Self {
+        self.phys_load_addr = phys_load_addr;
+        self
+    }
+
+    /// Sets the physical address of the start of the BSS, which is zeroed by the bootloader.
+    pub const fn phys_bss_start(mut self, phys_bss_start: u64) -> Self {
+        self.phys_bss_start = phys_bss_start;
+        self
+    }
+
+    /// Sets the physical address of the end of the BSS.
+    pub const fn phys_bss_end(mut self, phys_bss_end: u64) -> Self {
+        self.phys_bss_end = phys_bss_end;
+        self
+    }
+
+    /// Sets the physical address of the stivale2 header of the kernel.
+    pub const fn phys_stivale2hdr(mut self, phys_stivale2hdr: u64) -> Self {
+        self.phys_stivale2hdr = phys_stivale2hdr;
+        self
+    }
+
+    /// Returns the bitness of the kernel.
+    pub fn get_bits(&self) -> u8 {
+        self.bits
+    }
+
+    /// Returns the physical address at which the kernel image has to be loaded.
+    pub fn get_phys_load_addr(&self) -> u64 {
+        self.phys_load_addr
+    }
+
+    /// Returns the physical address of the start of the BSS.
+    pub fn get_phys_bss_start(&self) -> u64 {
+        self.phys_bss_start
+    }
+
+    /// Returns the physical address of the end of the BSS.
+    pub fn get_phys_bss_end(&self) -> u64 {
+        self.phys_bss_end
+    }
+
+    /// Returns the physical address of the stivale2 header of the kernel.
+    pub fn get_phys_stivale2hdr(&self) -> u64 {
+        self.phys_stivale2hdr
+    }
+
+    /// Scans the provided kernel image for an anchor on a 16-byte stride, returning the offset
+    /// of the first anchor along with its decoded contents. This is used by bootloaders to boot
+    /// kernels which are not ELF files.
+    pub fn find(image: &[u8]) -> Option<(usize, StivaleAnchor)> {
+        let size = core::mem::size_of::<StivaleAnchor>();
+
+        (0..image.len())
+            .step_by(16)
+            .filter(|&offset| offset + size <= image.len())
+            .find(|&offset| image[offset..].starts_with(&STIVALE2_ANCHOR_MAGIC))
+            .map(|offset| {
+                let bytes = &image[offset..offset + size];
+                let read_u64 = |field: usize| {
+                    let mut value = [0; 8];
+                    value.copy_from_slice(&bytes[16 + field * 8..24 + field * 8]);
+                    u64::from_le_bytes(value)
+                };
+
+                let anchor = StivaleAnchor::new()
+                    .bits(bytes[15])
+                    .phys_load_addr(read_u64(0))
+                    .phys_bss_start(read_u64(1))
+                    .phys_bss_end(read_u64(2))
+                    .phys_stivale2hdr(read_u64(3));
+
+                (offset, anchor)
+            })
+    }
+}
+
+impl Default for StivaleAnchor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anchor_layout() {
+        assert_eq!(core::mem::size_of::<StivaleAnchor>(), 48);
+        assert_eq!(core::mem::align_of::<StivaleAnchor>(), 16);
+
+        let anchor = StivaleAnchor::new().bits(32).phys_stivale2hdr(0x2000);
+        let bytes = unsafe {
+            core::slice::from_raw_parts(&anchor as *const StivaleAnchor as *const u8, 48)
+        };
+
+        assert_eq!(&bytes[..15], b"STIVALE2 ANCHOR");
+        assert_eq!(bytes[15], 32);
+        assert_eq!(&bytes[40..], &0x2000u64.to_le_bytes());
+    }
+
+    #[test]
+    fn find_anchor() {
+        let mut image = [0u8; 256];
+        // A misaligned copy of the magic must be skipped.
+        image[8..23].copy_from_slice(&STIVALE2_ANCHOR_MAGIC);
+        image[64..79].copy_from_slice(&STIVALE2_ANCHOR_MAGIC);
+        image[79] = 64;
+        image[80..88].copy_from_slice(&0x100000u64.to_le_bytes());
+        image[88..96].copy_from_slice(&0x180000u64.to_le_bytes());
+        image[96..104].copy_from_slice(&0x190000u64.to_le_bytes());
+        image[104..112].copy_from_slice(&0x100040u64.to_le_bytes());
+
+        let (offset, anchor) = StivaleAnchor::find(&image).unwrap();
+
+        assert_eq!(offset, 64);
+        assert_eq!(
+            anchor,
+            StivaleAnchor::new()
+                .phys_load_addr(0x100000)
+                .phys_bss_start(0x180000)
+                .phys_bss_end(0x190000)
+                .phys_stivale2hdr(0x100040)
+        );
+    }
+
+    #[test]
+    fn find_anchor_missing() {
+        let mut image = [0u8; 256];
+        // An anchor truncated by the end of the image is not reported.
+        image[224..239].copy_from_slice(&STIVALE2_ANCHOR_MAGIC);
+
+        assert_eq!(StivaleAnchor::find(&image), None);
+        assert_eq!(StivaleAnchor::find(&[]), None);
+    }
+}
@@ -0,0 +1,460 @@
+use core::fmt;
+use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+
+use super::*;
+
+/// Serialises the writes to the terminal made through [BootInfo::lock_terminal].
+static TERMINAL_LOCK: AtomicBool = AtomicBool::new(false);
+
+/// Set once the bootloader reclaimable memory has been handed out by [ReclaimPlan::reclaim],
+/// after which the terminal cannot be locked anymore.
+static RECLAIMED: AtomicBool = AtomicBool::new(false);
+
+/// Handle to the [StivaleStruct] which can be shared with the application processors.
+///
+/// The handle is `Send + Sync` and only exposes the tags which are never written to after boot,
+/// so they can be read concurrently from every CPU:
+///
+/// - The command line, memory map, modules, framebuffer, RSDP, SMBIOS, epoch, firmware, kernel
+///   file and kernel slide tags only describe the state of the machine at boot.
+/// - The terminal is not thread safe, so it is only reachable through
+///   [BootInfo::lock_terminal], which serialises the writes.
+/// - The SMP tag is not exposed, since the APs are started by writing to it. Read it from the
+///   BSP through [StivaleStruct::smp] before sharing the handle.
+#[derive(Clone, Copy)]
+pub struct BootInfo {
+    stivale_struct: &'static StivaleStruct,
+}
+
+impl BootInfo {
+    pub fn new(stivale_struct: &'static StivaleStruct) -> Self {
+        Self { stivale_struct }
+    }
+
+    /// Returns the bootloader brand and version. See [StivaleStruct::bootloader_info].
+    pub fn bootloader_info(&self) -> BootloaderInfo<'static> {
+        self.stivale_struct.bootloader_info()
+    }
+
+    pub fn command_line(&self) -> Option<&'static StivaleCommandLineTag> {
+        self.stivale_struct.command_line()
+    }
+
+    pub fn memory_map(&self) -> Option<&'static StivaleMemoryMapTag> {
+        self.stivale_struct.memory_map()
+    }
+
+    pub fn modules(&self) -> Option<&'static StivaleModuleTag> {
+        self.stivale_struct.modules()
+    }
+
+    pub fn framebuffer(&self) -> Option<&'static StivaleFramebufferTag> {
+        self.stivale_struct.framebuffer()
+    }
+
+    pub fn rsdp(&self) -> Option<&'static StivaleRsdpTag> {
+        self.stivale_struct.rsdp()
+    }
+
+    pub fn smbios(&self) -> Option<&'static StivaleSmbiosTag> {
+        self.stivale_struct.smbios()
+    }
+
+    pub fn epoch(&self) -> Option<&'static StivaleEpochTag> {
+        self.stivale_struct.epoch()
+    }
+
+    pub fn firmware(&self) -> Option<&'static StivaleFirmwareTag> {
+        self.stivale_struct.firmware()
+    }
+
+    pub fn kernel_file(&self) -> Option<&'static StivaleKernelFileTag> {
+        self.stivale_struct.kernel_file()
+    }
+
+    pub fn kernel_slide(&self) -> Option<&'static StivaleKernelSlideTag> {
+        self.stivale_struct.kernel_slide()
+    }
+
+    /// Locks the terminal, spinning until it is available, and returns a guard implementing
+    /// [fmt::Write]. Returns `None` if the bootloader did not provide a terminal, or if the
+    /// memory of the terminal has been reclaimed through [ReclaimPlan::reclaim].
+    ///
+    /// The lock only serialises the writes made through this function; writing to the
+    /// terminal through [StivaleStruct::terminal] at the same time is still a data race.
+    pub fn lock_terminal(&self) -> Option<TerminalGuard> {
+        let terminal = self.stivale_struct.terminal()?;
+
+        while TERMINAL_LOCK
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            // The lock is never released once the terminal has been reclaimed.
+            if RECLAIMED.load(Ordering::Acquire) {
+                return None;
+            }
+
+            core::hint::spin_loop();
+        }
+
+        Some(TerminalGuard { terminal })
+    }
+
+    /// Locks the terminal if it is available, without spinning. Returns `None` if the terminal
+    /// is already locked, has been reclaimed or if the bootloader did not provide one.
+    pub fn try_lock_terminal(&self) -> Option<TerminalGuard> {
+        let terminal = self.stivale_struct.terminal()?;
+
+        TERMINAL_LOCK
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| TerminalGuard { terminal })
+    }
+}
+
+/// Global holder for the [StivaleStruct], which is set once by the entry point of the kernel and
+/// can then be read from anywhere, for example by drivers.
+///
+/// ## Example
+/// ```rust,no_run
+/// use stivale_boot::v2::{BootInfoCell, StivaleStruct};
+///
+/// static BOOT_INFO: BootInfoCell = BootInfoCell::new();
+///
+/// extern "C" fn entry_point(stivale_struct: &'static StivaleStruct) -> ! {
+///     BOOT_INFO.set(stivale_struct).unwrap();
+///     loop {}
+/// }
+///
+/// fn framebuffer_driver() {
+///     let framebuffer = BOOT_INFO.get().and_then(|info| info.framebuffer());
+/// }
+/// ```
+pub struct BootInfoCell {
+    stivale_struct: AtomicPtr<StivaleStruct>,
+}
+
+impl BootInfoCell {
+    pub const fn new() -> Self {
+        Self {
+            stivale_struct: AtomicPtr::new(core::ptr::null_mut()),
+        }
+    }
+
+    /// Stores the provided structure in the cell. Returns the structure back as an error if the
+    /// cell was already set.
+    pub fn set(
+        &self,
+        stivale_struct: &'static StivaleStruct,
+    ) -> Result<(), &'static StivaleStruct> {
+        let ptr = stivale_struct as *const StivaleStruct as *mut StivaleStruct;
+
+        self.stivale_struct
+            .compare_exchange(
+                core::ptr::null_mut(),
+                ptr,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .map(|_| ())
+            .map_err(|_| stivale_struct)
+    }
+
+    /// Returns the structure stored in the cell, or `None` if the cell was not set yet.
+    pub fn get(&self) -> Option<&'static StivaleStruct> {
+        let ptr = self.stivale_struct.load(Ordering::Acquire);
+        unsafe { ptr.as_ref() }
+    }
+
+    /// Returns the structure stored in the cell, without checking whether it was set.
+    ///
+    /// ## Safety
+    /// [BootInfoCell::set] must have succeeded before calling this function.
+    pub unsafe fn get_unchecked(&self) -> &'static StivaleStruct {
+        &*self.stivale_struct.load(Ordering::Acquire)
+    }
+}
+
+impl Default for BootInfoCell {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Global [BootInfoCell], for kernels which do not want to declare their own. This requires the
+/// `global-boot-info` feature.
+#[cfg(feature = "global-boot-info")]
+pub static BOOT_INFO: BootInfoCell = BootInfoCell::new();
+
+/// Exclusive access to the terminal, returned by [BootInfo::lock_terminal]. The terminal is
+/// unlocked when the guard is dropped.
+pub struct TerminalGuard {
+    terminal: &'static StivaleTerminalTag,
+}
+
+impl fmt::Write for TerminalGuard {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.terminal.term_write()(s);
+        Ok(())
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        TERMINAL_LOCK.store(false, Ordering::Release);
+    }
+}
+
+/// Snapshot of the bootloader reclaimable regions, taken before any of them is reused.
+///
+/// The bootloader reclaimable memory holds the [StivaleStruct] and its tags, the terminal and
+/// the stacks of the APs, so reclaiming it has to be done in order:
+///
+/// 1. Copy out everything still needed from the boot information, for example the command line
+///    or the module names. The memory map itself is copied by [ReclaimPlan::new].
+/// 2. Make sure the APs have switched to their own stacks and no longer read their SMP tag
+///    entries.
+/// 3. Stop writing to the terminal and drop every [TerminalGuard]. This is enforced by
+///    [ReclaimPlan::reclaim], which fails if the terminal is locked and prevents it from being
+///    locked afterwards.
+/// 4. Call [ReclaimPlan::reclaim] to hand the regions to the memory manager.
+///
+/// ## Example
+/// ```rust,no_run
+/// use stivale_boot::v2::{MemoryRange, ReclaimPlan, StivaleStruct};
+///
+/// fn reclaim(stivale_struct: &'static StivaleStruct, free_range: impl FnMut(MemoryRange)) {
+///     let memory_map = stivale_struct.memory_map().expect("no memory map");
+///
+///     let mut buffer = [MemoryRange::default(); 16];
+///     let plan = ReclaimPlan::new(memory_map, &mut buffer).expect("too many regions");
+///
+///     // Nothing from `stivale_struct` may be used past this point.
+///     unsafe { plan.reclaim(free_range) }.ok().expect("the terminal is in use");
+/// }
+/// ```
+pub struct ReclaimPlan<'a> {
+    ranges: &'a [MemoryRange],
+}
+
+impl<'a> ReclaimPlan<'a> {
+    /// Copies the bootloader reclaimable regions of the memory map into the provided buffer.
+    /// Returns an error if the buffer cannot hold all the regions.
+    pub fn new(
+        memory_map: &StivaleMemoryMapTag,
+        buffer: &'a mut [MemoryRange],
+    ) -> Result<Self, ReclaimBufferTooSmall> {
+        let required = memory_map.reclaimable().count();
+
+        if required > buffer.len() {
+            return Err(ReclaimBufferTooSmall { required });
+        }
+
+        for (range, entry) in buffer.iter_mut().zip(memory_map.reclaimable()) {
+            *range = MemoryRange::new(entry.base, entry.length);
+        }
+
+        Ok(Self {
+            ranges: &buffer[..required],
+        })
+    }
+
+    /// Returns the regions to reclaim, in the order of the memory map.
+    pub fn ranges(&self) -> &'a [MemoryRange] {
+        self.ranges
+    }
+
+    /// Returns the sum of the lengths of the regions to reclaim.
+    pub fn total_bytes(&self) -> u64 {
+        self.ranges
+            .iter()
+            .fold(0u64, |total, range| total.saturating_add(range.length))
+    }
+
+    /// Passes each region to `free_range`, after locking the terminal for good. Returns the plan
+    /// back as an error, without calling `free_range`, if the terminal is currently locked.
+    ///
+    /// ## Safety
+    /// The steps listed in the [type-level documentation](ReclaimPlan) must have been followed:
+    /// every reference into the boot information, including the `'static` references returned
+    /// by [StivaleStruct] and [BootInfo], dangles once the regions are reused.
+    pub unsafe fn reclaim(self, free_range: impl FnMut(MemoryRange)) -> Result<(), Self> {
+        if TERMINAL_LOCK
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return Err(self);
+        }
+
+        RECLAIMED.store(true, Ordering::Release);
+        self.ranges.iter().copied().for_each(free_range);
+
+        Ok(())
+    }
+}
+
+/// Error returned by [ReclaimPlan::new] if the buffer is too small to hold all the bootloader
+/// reclaimable regions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReclaimBufferTooSmall {
+    /// The number of bootloader reclaimable regions in the memory map.
+    pub required: usize,
+}
+
+impl fmt::Display for ReclaimBufferTooSmall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "buffer too small for {} reclaimable regions",
+            self.required
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::test_utils::v2::{dst_tag, entry, memory_map};
+    use core::fmt::Write;
+    use core::sync::atomic::AtomicU64;
+    use std::{boxed::Box, string::ToString};
+
+    static WRITTEN: AtomicU64 = AtomicU64::new(0);
+
+    extern "C" fn term_write(_: *const i8, len: u64) {
+        WRITTEN.fetch_add(len, Ordering::Relaxed);
+    }
+
+    #[repr(C, align(8))]
+    struct Buffer([u8; 256]);
+
+    fn boot_info() -> BootInfo {
+        let buffer = Box::leak(Box::new(Buffer([0; 256])));
+        let mut writer = writer::StructWriter::new(&mut buffer.0, "Limine", "2.0").unwrap();
+        let terminal = StivaleTerminalTag {
+            header: StivaleTagHeader {
+                identifier: tag_ids::TERMINAL,
+                next: 0,
+            },
+            flags: 0,
+            cols: 80,
+            rows: 25,
+            term_write_addr: term_write as *const () as u64,
+        };
+
+        unsafe {
+            writer.push_tag(&terminal).unwrap();
+            BootInfo::new(&*(writer.struct_address() as *const StivaleStruct))
+        }
+    }
+
+    #[test]
+    fn boot_info_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<BootInfo>();
+    }
+
+    #[test]
+    fn boot_info_cell_set_once() {
+        static FIRST: StivaleStruct = StivaleStruct::new();
+        static SECOND: StivaleStruct = StivaleStruct::new();
+
+        let cell = BootInfoCell::new();
+        assert!(cell.get().is_none());
+
+        assert!(cell.set(&FIRST).is_ok());
+        assert!(core::ptr::eq(cell.set(&SECOND).unwrap_err(), &SECOND));
+
+        assert!(core::ptr::eq(cell.get().unwrap(), &FIRST));
+        assert!(core::ptr::eq(unsafe { cell.get_unchecked() }, &FIRST));
+    }
+
+    #[test]
+    fn boot_info_cell_concurrent_get() {
+        static CELL: BootInfoCell = BootInfoCell::new();
+        static STIVALE_STRUCT: StivaleStruct = StivaleStruct::new();
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    // Spin until the main thread has set the cell.
+                    let stivale_struct = loop {
+                        if let Some(stivale_struct) = CELL.get() {
+                            break stivale_struct;
+                        }
+
+                        core::hint::spin_loop();
+                    };
+
+                    assert!(core::ptr::eq(stivale_struct, &STIVALE_STRUCT));
+                });
+            }
+
+            CELL.set(&STIVALE_STRUCT).unwrap();
+        });
+    }
+
+    #[test]
+    fn reclaim_plan_snapshot() {
+        let mut buf = dst_tag(
+            tag_ids::MEMORY_MAP,
+            &[
+                entry(0x1000, 0x9f000, StivaleMemoryMapEntryType::Usable),
+                entry(
+                    0x100000,
+                    0x2000,
+                    StivaleMemoryMapEntryType::BootloaderReclaimable,
+                ),
+                entry(
+                    0x200000,
+                    0x10000,
+                    StivaleMemoryMapEntryType::BootloaderReclaimable,
+                ),
+            ],
+        );
+        let memory_map = memory_map(&mut buf);
+
+        let mut buffer = [MemoryRange::new(0xdead, 0xbeef); 3];
+        let plan = ReclaimPlan::new(memory_map, &mut buffer).unwrap();
+
+        assert_eq!(
+            plan.ranges(),
+            [
+                MemoryRange::new(0x100000, 0x2000),
+                MemoryRange::new(0x200000, 0x10000)
+            ]
+        );
+        assert_eq!(plan.total_bytes(), 0x12000);
+        // The rest of the buffer is left untouched.
+        assert_eq!(buffer[2], MemoryRange::new(0xdead, 0xbeef));
+
+        let mut buffer = [MemoryRange::default(); 1];
+        let error = ReclaimPlan::new(memory_map, &mut buffer).err().unwrap();
+
+        assert_eq!(error, ReclaimBufferTooSmall { required: 2 });
+        assert_eq!(
+            error.to_string(),
+            "buffer too small for 2 reclaimable regions"
+        );
+        assert!(ReclaimPlan::new(memory_map, &mut []).is_err());
+    }
+
+    #[test]
+    fn terminal_lock() {
+        let boot_info = boot_info();
+        assert_eq!(boot_info.bootloader_info().brand, Some("Limine"));
+        assert!(boot_info.memory_map().is_none());
+
+        let mut guard = boot_info.lock_terminal().unwrap();
+        assert!(boot_info.try_lock_terminal().is_none());
+
+        guard.write_str("Hello").unwrap();
+        assert_eq!(WRITTEN.load(Ordering::Relaxed), 5);
+
+        drop(guard);
+        assert!(boot_info.try_lock_terminal().is_some());
+    }
+}
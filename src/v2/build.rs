@@ -0,0 +1,247 @@
+//! Host-side builder for stivale2 boot information, which lets kernels unit test the code
+//! consuming the [StivaleStruct] without a bootloader. This module requires the `alloc` feature.
+//!
+//! ## Example
+//! ```rust
+//! use stivale_boot::v2::build::BootInfoBuilder;
+//! use stivale_boot::v2::StivaleMemoryMapEntryType;
+//!
+//! let boot_info = BootInfoBuilder::new()
+//!     .brand("Limine")
+//!     .version("2.0")
+//!     .command_line("root=/dev/sda1")
+//!     .memory_region(0x0, 0x1000, StivaleMemoryMapEntryType::Reserved)
+//!     .memory_region(0x1000, 0x9f000, StivaleMemoryMapEntryType::Usable)
+//!     .memory_region(0x100000, 0x100000, StivaleMemoryMapEntryType::Kernel)
+//!     .module("initrd", b"initrd contents")
+//!     .module("font.psf", &[0x72, 0xb5, 0x4a, 0x86])
+//!     .framebuffer(640, 480, 2560, 32)
+//!     .build();
+//!
+//! let stivale_struct = boot_info.leak();
+//! assert_eq!(stivale_struct.bootloader_brand(), "Limine");
+//!
+//! let memory_map = stivale_struct.memory_map().unwrap();
+//! assert_eq!(memory_map.iter().count(), 3);
+//! assert_eq!(memory_map.as_slice()[1].base, 0x1000);
+//!
+//! let modules = stivale_struct.modules().unwrap();
+//! assert_eq!(modules.as_slice()[0].as_str(), "initrd");
+//! assert_eq!(modules.as_slice()[1].size(), 4);
+//!
+//! let framebuffer = stivale_struct.framebuffer().unwrap();
+//! assert_eq!(framebuffer.framebuffer_width, 640);
+//! assert_eq!(framebuffer.size(), 2560 * 480);
+//! ```
+
+use alloc::{boxed::Box, string::String, vec, vec::Vec};
+use core::{mem, slice};
+
+use super::writer::StructWriter;
+use super::{
+    tag_ids, MaskInfo, StivaleFramebufferTag, StivaleMemoryMapEntry, StivaleMemoryMapEntryType,
+    StivaleModule, StivaleStruct, StivaleTagHeader,
+};
+
+/// Builder for a [BootInfoBuffer], containing a [StivaleStruct] and the tags that were added to
+/// the builder. See the [module-level documentation](self) for an example.
+#[derive(Default)]
+pub struct BootInfoBuilder {
+    brand: String,
+    version: String,
+    command_line: Option<String>,
+    memory_map: Vec<(u64, u64, StivaleMemoryMapEntryType)>,
+    modules: Vec<(String, Vec<u8>)>,
+    framebuffer: Option<(u16, u16, u16, u16)>,
+}
+
+impl BootInfoBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the bootloader brand.
+    ///
+    /// ## Panics
+    /// Panics if the brand is longer than 63 bytes, as it has to be 0-terminated.
+    pub fn brand(mut self, brand: &str) -> Self {
+        assert!(brand.len() < 64, "bootloader brand is too long");
+
+        self.brand = brand.into();
+        self
+    }
+
+    /// Sets the bootloader version.
+    ///
+    /// ## Panics
+    /// Panics if the version is longer than 63 bytes, as it has to be 0-terminated.
+    pub fn version(mut self, version: &str) -> Self {
+        assert!(version.len() < 64, "bootloader version is too long");
+
+        self.version = version.into();
+        self
+    }
+
+    /// Adds a command line tag containing the provided command line.
+    pub fn command_line(mut self, command_line: &str) -> Self {
+        self.command_line = Some(command_line.into());
+        self
+    }
+
+    /// Adds an entry to the memory map tag. The entries are stored in the order they were added,
+    /// and the memory map tag is only present if at least one entry was added.
+    pub fn memory_region(
+        mut self,
+        base: u64,
+        length: u64,
+        entry_type: StivaleMemoryMapEntryType,
+    ) -> Self {
+        self.memory_map.push((base, length, entry_type));
+        self
+    }
+
+    /// Adds a module to the modules tag. The payload is copied into the boot information buffer
+    /// and the module points to that copy.
+    ///
+    /// ## Panics
+    /// Panics if the name is longer than 127 bytes, as it has to be 0-terminated.
+    pub fn module(mut self, name: &str, payload: &[u8]) -> Self {
+        assert!(name.len() < 128, "module name is too long");
+
+        self.modules.push((name.into(), payload.into()));
+        self
+    }
+
+    /// Adds a framebuffer tag with the provided mode, using a 8:8:8 RGB memory model. Zeroed
+    /// framebuffer memory is allocated in the boot information buffer, so that the pixel helpers
+    /// of [super::StivaleFramebufferTag] can be used.
+    pub fn framebuffer(mut self, width: u16, height: u16, pitch: u16, bpp: u16) -> Self {
+        self.framebuffer = Some((width, height, pitch, bpp));
+        self
+    }
+
+    /// Lays out the structure, the tags and their payloads in a single buffer.
+    pub fn build(self) -> BootInfoBuffer {
+        let framebuffer_size = self
+            .framebuffer
+            .map_or(0, |(_, height, pitch, _)| pitch as usize * height as usize);
+
+        let mut sizes = vec![
+            mem::size_of::<StivaleStruct>(),
+            framebuffer_size,
+            mem::size_of::<StivaleFramebufferTag>(),
+            // The command line tag is made of the address of the command line.
+            mem::size_of::<StivaleTagHeader>() + mem::size_of::<u64>(),
+            self.command_line
+                .as_ref()
+                .map_or(0, |command_line| command_line.len() + 1),
+            // The memory map and modules tags are made of an entry count and the entries.
+            mem::size_of::<StivaleTagHeader>()
+                + mem::size_of::<u64>()
+                + self.memory_map.len() * mem::size_of::<StivaleMemoryMapEntry>(),
+            mem::size_of::<StivaleTagHeader>()
+                + mem::size_of::<u64>()
+                + self.modules.len() * mem::size_of::<StivaleModule>(),
+        ];
+        sizes.extend(self.modules.iter().map(|(_, payload)| payload.len()));
+
+        let length = sizes
+            .iter()
+            .map(|size| size.next_multiple_of(8))
+            .sum::<usize>();
+        let mut words = vec![0u64; length / 8].into_boxed_slice();
+
+        // SAFETY: the buffer is made of `length` initialised bytes, and the heap allocation does
+        // not move when the box is moved into the returned buffer.
+        let buf = unsafe { slice::from_raw_parts_mut(words.as_mut_ptr() as *mut u8, length) };
+        let mut writer = StructWriter::new(buf, &self.brand, &self.version)
+            .expect("the builder checks the brand and version lengths");
+
+        // The buffer is sized for every tag the builder may write, whether present or not.
+        let error = "boot information buffer too small";
+
+        if let Some(command_line) = &self.command_line {
+            let mut bytes = command_line.clone().into_bytes();
+            bytes.push(0);
+
+            let address = writer.push_data(&bytes).expect(error);
+            writer
+                .push_raw_tag(tag_ids::COMMAND_LINE, &address.to_ne_bytes())
+                .expect(error);
+        }
+
+        if !self.memory_map.is_empty() {
+            let entries = self
+                .memory_map
+                .iter()
+                .map(|&(base, length, entry_type)| {
+                    StivaleMemoryMapEntry::new(base, length, entry_type)
+                })
+                .collect::<Vec<_>>();
+
+            writer.push_memory_map(&entries).expect(error);
+        }
+
+        if let Some((width, height, pitch, bpp)) = self.framebuffer {
+            let address = writer.push_data(&vec![0; framebuffer_size]).expect(error);
+            let tag = StivaleFramebufferTag::new(
+                address,
+                width,
+                height,
+                pitch,
+                bpp,
+                MaskInfo { size: 8, shift: 16 },
+                MaskInfo { size: 8, shift: 8 },
+                MaskInfo { size: 8, shift: 0 },
+            );
+
+            // SAFETY: the framebuffer tag starts with a tag header and has explicit padding.
+            unsafe { writer.push_tag(&tag) }.expect(error);
+        }
+
+        if !self.modules.is_empty() {
+            let mut modules = Vec::with_capacity(self.modules.len());
+
+            for (name, payload) in &self.modules {
+                let start = writer.push_data(payload).expect(error);
+                let mut string = [0; 128];
+                string[..name.len()].copy_from_slice(name.as_bytes());
+
+                modules.push(StivaleModule {
+                    start,
+                    end: start + payload.len() as u64,
+                    string,
+                });
+            }
+
+            writer.push_modules(&modules).expect(error);
+        }
+
+        BootInfoBuffer { words }
+    }
+}
+
+/// Owned buffer containing a [StivaleStruct] and its tags, created by a [BootInfoBuilder].
+pub struct BootInfoBuffer {
+    words: Box<[u64]>,
+}
+
+impl BootInfoBuffer {
+    /// Returns the stivale2 structure at the start of this buffer. Prefer [BootInfoBuffer::leak]
+    /// unless the buffer has to be freed.
+    ///
+    /// ## Safety
+    /// The tag accessors of [StivaleStruct] return references with a `'static` lifetime, since
+    /// bootloader memory is never freed. None of the references obtained through the returned
+    /// structure may be used after the buffer has been dropped.
+    pub unsafe fn stivale_struct(&self) -> &StivaleStruct {
+        &*(self.words.as_ptr() as *const StivaleStruct)
+    }
+
+    /// Leaks this buffer and returns the stivale2 structure at its start, which then stays valid
+    /// for the rest of the program like one passed by a bootloader.
+    pub fn leak(self) -> &'static StivaleStruct {
+        let words = Box::leak(self.words);
+        unsafe { &*(words.as_ptr() as *const StivaleStruct) }
+    }
+}
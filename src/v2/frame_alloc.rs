@@ -0,0 +1,145 @@
+//! Early boot physical frame allocator, for use before the kernel has set up its own memory
+//! manager. This module requires the `frame-alloc` feature.
+//!
+//! ## Example
+//! ```rust,no_run
+//! use stivale_boot::v2::frame_alloc::BumpFrameAllocator;
+//! use stivale_boot::v2::StivaleStruct;
+//!
+//! fn kmain(stivale_struct: &'static StivaleStruct) {
+//!     let memory_map = stivale_struct.memory_map().expect("no memory map");
+//!     // The kernel image and the initrd must not be handed out.
+//!     let excluded = [(0x200000, 0x100000), (0x400000, 0x80000)];
+//!     let mut allocator = BumpFrameAllocator::with_excluded(memory_map, &excluded);
+//!
+//!     let page_table = allocator.allocate_frame().expect("out of physical memory");
+//! }
+//! ```
+
+use core::iter::{Copied, Map};
+use core::slice;
+
+use super::{StivaleMemoryMapEntry, StivaleMemoryMapTag, SubtractedMapIter};
+use crate::common::{FrameWalker, MemoryRange};
+
+/// The size of the frames handed out by a [BumpFrameAllocator].
+pub const FRAME_SIZE: u64 = 0x1000;
+
+/// The usable regions of the memory map, with the excluded ranges removed.
+type Regions<'a> = Map<
+    SubtractedMapIter<'a, Copied<slice::Iter<'a, (u64, u64)>>>,
+    fn(StivaleMemoryMapEntry) -> MemoryRange,
+>;
+
+/// Allocator handing out the 4 KiB frames of the usable memory regions in ascending order.
+/// Frames are never freed, so the allocator is meant to be replaced by the memory manager of
+/// the kernel once it is up. The allocator is not [Clone], as a copy would hand out the same
+/// frames again.
+pub struct BumpFrameAllocator<'a> {
+    /// The frames which have not been handed out yet.
+    frames: FrameWalker<Regions<'a>>,
+    /// The number of frames handed out so far.
+    allocated: u64,
+}
+
+impl<'a> BumpFrameAllocator<'a> {
+    /// Creates an allocator over all the usable regions of the provided memory map.
+    pub fn new(memory_map: &'a StivaleMemoryMapTag) -> Self {
+        Self::with_excluded(memory_map, &[])
+    }
+
+    /// Creates an allocator over the usable regions of the provided memory map, skipping the
+    /// excluded `(base, length)` ranges, for example the kernel image and the modules.
+    pub fn with_excluded(memory_map: &'a StivaleMemoryMapTag, excluded: &'a [(u64, u64)]) -> Self {
+        let regions: Regions<'a> = memory_map
+            .subtract_regions(excluded.iter().copied())
+            .map(|region| region.range());
+
+        Self {
+            frames: FrameWalker::new(regions, FRAME_SIZE),
+            allocated: 0,
+        }
+    }
+
+    /// Returns the base address of a new frame, or `None` once all the usable memory has been
+    /// handed out.
+    pub fn allocate_frame(&mut self) -> Option<u64> {
+        let frame = self.frames.next()?;
+        self.allocated += 1;
+
+        Some(frame)
+    }
+
+    /// Returns the number of frames handed out so far.
+    pub fn allocated_frames(&self) -> u64 {
+        self.allocated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::test_utils::v2::{dst_tag, entry, memory_map};
+    use crate::v2::{tag_ids, StivaleMemoryMapEntryType};
+    use std::vec::Vec;
+
+    fn sample_memory_map() -> Vec<u64> {
+        dst_tag(
+            tag_ids::MEMORY_MAP,
+            &[
+                entry(0x0, 0x1000, StivaleMemoryMapEntryType::Reserved),
+                // Not aligned to the frame size, so only two whole frames are usable.
+                entry(0x1800, 0x2800, StivaleMemoryMapEntryType::Usable),
+                entry(0x100000, 0x4000, StivaleMemoryMapEntryType::Kernel),
+                entry(0x200000, 0x4000, StivaleMemoryMapEntryType::Usable),
+            ],
+        )
+    }
+
+    #[test]
+    fn allocate_all_frames() {
+        let mut buf = sample_memory_map();
+        let mut allocator = BumpFrameAllocator::new(memory_map(&mut buf));
+        let frames = core::iter::from_fn(|| allocator.allocate_frame()).collect::<Vec<_>>();
+
+        assert_eq!(
+            frames,
+            [0x2000, 0x3000, 0x200000, 0x201000, 0x202000, 0x203000]
+        );
+        assert_eq!(allocator.allocated_frames(), 6);
+
+        // The allocator stays exhausted.
+        assert_eq!(allocator.allocate_frame(), None);
+        assert_eq!(allocator.allocated_frames(), 6);
+    }
+
+    #[test]
+    fn allocate_skips_excluded_ranges() {
+        let mut buf = sample_memory_map();
+        let excluded = [(0x2000, 0x1000), (0x201000, 0x2000)];
+        let mut allocator = BumpFrameAllocator::with_excluded(memory_map(&mut buf), &excluded);
+        let frames = core::iter::from_fn(|| allocator.allocate_frame()).collect::<Vec<_>>();
+
+        assert_eq!(frames, [0x3000, 0x200000, 0x203000]);
+    }
+
+    #[test]
+    fn allocate_at_the_top_of_the_address_space() {
+        // The last frame is not whole, and handing it out would wrap the next frame address.
+        let mut buf = dst_tag(
+            tag_ids::MEMORY_MAP,
+            &[entry(
+                u64::MAX - 0x2fff,
+                0x2fff,
+                StivaleMemoryMapEntryType::Usable,
+            )],
+        );
+        let mut allocator = BumpFrameAllocator::new(memory_map(&mut buf));
+
+        assert_eq!(allocator.allocate_frame(), Some(u64::MAX - 0x2fff));
+        assert_eq!(allocator.allocate_frame(), Some(u64::MAX - 0x1fff));
+        assert_eq!(allocator.allocate_frame(), None);
+    }
+}
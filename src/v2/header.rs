@@ -1,16 +1,24 @@
-use super::StivaleStruct;
+//! Definitions of the stivale2 header and header tags, which the kernel provides to the
+//! bootloader, along with helpers for bootloaders to parse them back.
+
+use core::fmt;
+
+use super::{StivaleStruct, StivaleTagHeader, TAG_COUNT_MAX};
 
 macro_rules! make_header_tag {
 	($(#[$meta:meta])* struct $name:ident: $id:expr;) => {
 		make_header_tag!($(#[$meta])* struct $name: $id => {};);
 	};
 
-	($(#[$meta:meta])* struct $name:ident: $id:expr => {$($(#[$field_meta:meta])* $field_name:ident : $field_ty:ty = $field_default:expr),*};) => {
+	($(#[$meta:meta])* struct $name:ident: $id:expr => {$($(#[$field_meta:meta])* $field_name:ident : $field_ty:ty = $field_default:expr $(; auto = $field_auto:ident)?),*};) => {
         $(#[$meta])*
         #[repr(C, packed)]
+        #[cfg_attr(feature = "extra-traits", derive(Clone, Copy, Debug, PartialEq, Eq, Hash))]
         pub struct $name {
 			identifier: u64,
 			next: *const (),
+			#[cfg(target_pointer_width = "32")]
+			_next_padding: u32,
 			$($field_name: $field_ty),*
 		}
 
@@ -18,10 +26,15 @@ macro_rules! make_header_tag {
         #[allow(deprecated)] unsafe impl Sync for $name {}
 
         #[allow(deprecated)] impl $name {
+			/// The identifier of this header tag.
+			pub const IDENTIFIER: u64 = $id;
+
 			pub const fn new() -> Self {
 				Self {
 					identifier: $id,
 					next: core::ptr::null(),
+					#[cfg(target_pointer_width = "32")]
+					_next_padding: 0,
 					$($field_name: $field_default),*
 				}
 			}
@@ -35,30 +48,66 @@ macro_rules! make_header_tag {
 				self.$field_name = value;
 				self
 			})*
+
+			$($(
+				#[doc = concat!("Sets `", stringify!($field_name), "` to zero, which lets the bootloader pick the value.")]
+				pub const fn $field_auto(mut self) -> Self {
+					self.$field_name = 0;
+					self
+				}
+			)?)*
+		}
+
+        #[allow(deprecated)] impl Default for $name {
+			fn default() -> Self {
+				Self::new()
+			}
 		}
 	};
 }
 
+/// The entry point field of the header, which is either a function pointer or zero.
+///
+/// Every variant is pointer sized and fully initialises the union, so the header never contains
+/// uninitialised bytes. Writing a union field is safe, so the union is only read through
+/// `unsafe` code where a variant is reinterpreted.
+#[cfg_attr(feature = "extra-traits", derive(Clone, Copy))]
 union StivaleHeaderEntryPoint {
     func: extern "C" fn(&'static StivaleStruct) -> !,
-    zero: u64,
+    zero: usize,
+    ptr: *const (),
 }
 
 #[repr(C, packed)]
+#[cfg_attr(feature = "extra-traits", derive(Clone, Copy))]
 pub struct StivaleHeader {
     entry_point: StivaleHeaderEntryPoint,
+    // The protocol uses 64-bit fields for pointers, even for 32-bit kernels.
+    #[cfg(target_pointer_width = "32")]
+    _entry_point_padding: u32,
     stack: *const u8,
+    #[cfg(target_pointer_width = "32")]
+    _stack_padding: u32,
     flags: u64,
     tags: *const (),
+    #[cfg(target_pointer_width = "32")]
+    _tags_padding: u32,
 }
 
 impl StivaleHeader {
     pub const fn new() -> Self {
         Self {
+            // A zeroed entry point tells the bootloader to use the entry point of the kernel ELF.
             entry_point: StivaleHeaderEntryPoint { zero: 0 },
+            #[cfg(target_pointer_width = "32")]
+            _entry_point_padding: 0,
             stack: core::ptr::null(),
+            #[cfg(target_pointer_width = "32")]
+            _stack_padding: 0,
             flags: 0,
             tags: core::ptr::null(),
+            #[cfg(target_pointer_width = "32")]
+            _tags_padding: 0,
         }
     }
 
@@ -67,12 +116,51 @@ impl StivaleHeader {
         self.stack
     }
 
+    /// Returns `false` if neither a stack nor an entry point has been set in this header.
+    ///
+    /// 64-bit kernels may leave both unset, in which case the bootloader uses the ELF entry
+    /// point and does not set up a stack, but a 32-bit kernel always needs at least one of them.
+    /// This is looser than the stivale `v1::StivaleHeader::validate_for_32bit`, which requires a
+    /// stack even if an entry point is set.
+    /// See [stivale_assert_valid_32bit!](crate::stivale_assert_valid_32bit) for checking this at
+    /// compile time.
+    pub const fn validate_for_32bit(&self) -> bool {
+        // SAFETY: every variant of the union is pointer sized, so reading it back as a pointer
+        // is sound regardless of which one was written.
+        !self.stack.is_null() || unsafe { !self.entry_point.ptr.is_null() }
+    }
+
     /// Returns the flags stored in this header.
     pub fn get_flags(&self) -> u64 {
         self.flags
     }
 
+    /// Returns an iterator over the addresses of the header tags chained from this header,
+    /// following the `next` pointer of each tag. The iteration stops after [TAG_COUNT_MAX] tags to
+    /// guard against malformed (or cyclic) chains.
+    ///
+    /// ## Safety
+    /// The `tags` pointer of this header and the `next` pointer of every tag in the chain must be
+    /// null or point to a valid header tag, since the iterator reads the header of each tag.
+    pub unsafe fn tags_iter(&self) -> StivaleHeaderTagIter {
+        StivaleHeaderTagIter {
+            current: self.tags,
+            remaining: TAG_COUNT_MAX,
+        }
+    }
+
+    /// Returns the number of header tags chained from this header, up to [TAG_COUNT_MAX]. See
+    /// [StivaleHeader::tags_iter] for more information.
+    ///
+    /// ## Safety
+    /// Every pointer in the chain must be null or point to a valid header tag. See
+    /// [StivaleHeader::tags_iter].
+    pub unsafe fn tags_chain_len(&self) -> usize {
+        self.tags_iter().count()
+    }
+
     pub const fn entry_point(mut self, func: extern "C" fn(&'static StivaleStruct) -> !) -> Self {
+        // Overwriting the whole union is safe, and `func` has the same size as `zero`.
         self.entry_point = StivaleHeaderEntryPoint { func };
         self
     }
@@ -93,14 +181,103 @@ impl StivaleHeader {
     }
 }
 
+impl Default for StivaleHeader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "extra-traits")]
+impl fmt::Debug for StivaleHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The header is packed, so the fields are copied out before being formatted.
+        let (stack, flags, tags) = (self.stack, self.flags, self.tags);
+        // SAFETY: every variant of the union is pointer sized.
+        let entry_point = unsafe { self.entry_point.ptr };
+
+        f.debug_struct("StivaleHeader")
+            .field("entry_point", &entry_point)
+            .field("stack", &stack)
+            .field("flags", &flags)
+            .field("tags", &tags)
+            .finish()
+    }
+}
+
+/// Asserts at compile time that the provided header is valid for a 32-bit kernel. See
+/// [StivaleHeader::validate_for_32bit] for more information.
+///
+/// The header has to be usable in a constant context, for example a `const` or an immutable
+/// `static`. When the `helper-macros` feature is enabled, `#[stivale2hdr]` performs this check
+/// automatically when targeting `x86`.
+///
+/// ## Usage
+/// ```rust
+/// use stivale_boot::v2::StivaleHeader;
+/// use stivale_boot::stivale_assert_valid_32bit;
+///
+/// static STACK: [u8; 4096] = [0; 4096];
+///
+/// const HEADER: StivaleHeader = StivaleHeader::new().stack(STACK.as_ptr());
+///
+/// stivale_assert_valid_32bit!(HEADER);
+/// ```
+#[macro_export]
+macro_rules! stivale_assert_valid_32bit {
+    ($header:expr) => {
+        const _: () = assert!(
+            $header.validate_for_32bit(),
+            "the stivale2 header needs a stack or an entry point on 32-bit targets"
+        );
+    };
+}
+
+/// Iterator over the addresses of the header tags chained from a [StivaleHeader].
+///
+/// The iterator can only be created through the `unsafe` [StivaleHeader::tags_iter], whose caller
+/// guarantees that every pointer in the chain is null or points to a valid header tag.
+#[derive(Clone)]
+pub struct StivaleHeaderTagIter {
+    /// The address of the header tag that we are about to yield.
+    current: *const (),
+    /// The number of tags that may still be yielded before giving up on the chain.
+    remaining: usize,
+}
+
+impl Iterator for StivaleHeaderTagIter {
+    type Item = *const ();
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current.is_null() || self.remaining == 0 {
+            return None;
+        }
+
+        let tag = self.current;
+        // Header tags are packed, so the tag header might not be aligned.
+        // SAFETY: the caller of `tags_iter` guarantees that the tag is valid.
+        let header = unsafe { (tag as *const StivaleTagHeader).read_unaligned() };
+
+        self.current = header.next as *const ();
+        self.remaining -= 1;
+
+        Some(tag)
+    }
+}
+
 make_header_tag!(
     /// If this tag is present the bootloader is instructed to initialise a graphical
     /// framebuffer video mode. Omitting this tag will make the bootloader default to a
     /// CGA-compatible text mode, if supported.
     struct StivaleFramebufferHeaderTag: 0x3ecc1bc43d0f7971 => {
-        framebuffer_width: u16 = 0,
-        framebuffer_height: u16 = 0,
-        framebuffer_bpp: u16 = 0,
+        /// Sets the requested framebuffer width. If set to zero, the bootloader would pick
+        /// the best possible value automatically (recommended).
+        framebuffer_width: u16 = 0; auto = framebuffer_width_auto,
+        /// Sets the requested framebuffer height. If set to zero, the bootloader would pick
+        /// the best possible value automatically (recommended).
+        framebuffer_height: u16 = 0; auto = framebuffer_height_auto,
+        /// Sets the requested framebuffer bpp. If set to zero, the bootloader would pick
+        /// the best possible value automatically (recommended).
+        framebuffer_bpp: u16 = 0; auto = framebuffer_bpp_auto,
         _padding: u16 = 0
     };
 );
@@ -131,6 +308,7 @@ make_header_tag!(
     };
 );
 
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 make_header_tag!(
     /// This tag tells the bootloader to, in case a framebuffer was requested, make that framebuffer's
     /// caching type write-combining using x86's MTRR model specific registers. This caching type helps speed
@@ -142,9 +320,10 @@ make_header_tag!(
     struct StivaleMtrrHeaderTag: 0x4c7bb07731282e00;
 );
 
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 make_header_tag!(
     /// If this tag is present the bootloader is instructed to enable upport for 5-level paging, if
-    /// available.
+    /// available. This tag is only available on x86.
     struct Stivale5LevelPagingHeaderTag: 0x932f477032007e8f;
 );
 
@@ -184,3 +363,302 @@ make_header_tag!(
 
 unsafe impl Send for StivaleHeader {}
 unsafe impl Sync for StivaleHeader {}
+
+/// Error returned when parsing a stivale2 header or its header tags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeaderParseError {
+    /// The provided bytes are too short to contain the header or the header tag.
+    TooShort,
+    /// The translation callback could not provide the bytes at this kernel address.
+    UnmappedAddress(u64),
+}
+
+impl fmt::Display for HeaderParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderParseError::TooShort => f.write_str("truncated stivale2 header"),
+            HeaderParseError::UnmappedAddress(address) => {
+                write!(f, "unmapped stivale2 header tag at {:#x}", address)
+            }
+        }
+    }
+}
+
+/// A stivale2 header decoded from the `.stivale2hdr` section of a kernel. All the addresses are
+/// kernel addresses, as specified at link time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParsedHeader {
+    /// The entry point requested by the kernel, or zero to use the ELF entry point.
+    pub entry_point: u64,
+    /// The stack requested by the kernel, or zero if no stack should be set up.
+    pub stack: u64,
+    pub flags: u64,
+    /// The address of the first header tag, or zero if there is none.
+    pub tags: u64,
+}
+
+impl ParsedHeader {
+    /// Returns an iterator over the header tags of this header. As the tags live at kernel
+    /// addresses, `translate` is called with the address of each tag and has to return the
+    /// bytes of the loaded kernel starting at that address, or `None` if the address is not part
+    /// of the kernel. The iteration stops after [TAG_COUNT_MAX] tags or after the first error.
+    pub fn tags_iter<'a, F>(&self, translate: F) -> HeaderTagIter<'a, F>
+    where
+        F: FnMut(u64) -> Option<&'a [u8]>,
+    {
+        HeaderTagIter {
+            translate,
+            next: self.tags,
+            remaining: TAG_COUNT_MAX,
+        }
+    }
+}
+
+/// Parses a stivale2 header from the contents of the `.stivale2hdr` section of a kernel.
+pub fn parse(bytes: &[u8]) -> Result<ParsedHeader, HeaderParseError> {
+    Ok(ParsedHeader {
+        entry_point: read_u64(bytes, 0)?,
+        stack: read_u64(bytes, 8)?,
+        flags: read_u64(bytes, 16)?,
+        tags: read_u64(bytes, 24)?,
+    })
+}
+
+/// Kind of a header tag, as recognised from its identifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HeaderTagKind {
+    Framebuffer,
+    Terminal,
+    Smp,
+    Mtrr,
+    FiveLevelPaging,
+    UnmapNull,
+    AnyVideo,
+    SlideHhdm,
+    /// A header tag unknown to this crate, with its raw identifier.
+    Unknown(u64),
+}
+
+impl HeaderTagKind {
+    // The MTRR and 5-level paging tags are only defined on x86, but bootloaders may parse x86
+    // kernels on any architecture.
+    const MTRR: u64 = 0x4c7bb07731282e00;
+    const FIVE_LEVEL_PAGING: u64 = 0x932f477032007e8f;
+
+    /// Returns the kind of header tag with the provided identifier.
+    pub fn from_identifier(identifier: u64) -> Self {
+        match identifier {
+            StivaleFramebufferHeaderTag::IDENTIFIER => HeaderTagKind::Framebuffer,
+            StivaleTerminalHeaderTag::IDENTIFIER => HeaderTagKind::Terminal,
+            StivaleSmpHeaderTag::IDENTIFIER => HeaderTagKind::Smp,
+            Self::MTRR => HeaderTagKind::Mtrr,
+            Self::FIVE_LEVEL_PAGING => HeaderTagKind::FiveLevelPaging,
+            StivaleUnmapNullHeaderTag::IDENTIFIER => HeaderTagKind::UnmapNull,
+            StivaleAnyVideoTag::IDENTIFIER => HeaderTagKind::AnyVideo,
+            StivaleSlideHddmHeaderTag::IDENTIFIER => HeaderTagKind::SlideHhdm,
+            identifier => HeaderTagKind::Unknown(identifier),
+        }
+    }
+}
+
+/// A header tag found while walking the header tag chain of a [ParsedHeader].
+#[derive(Clone, Copy, Debug)]
+pub struct ParsedHeaderTag<'a> {
+    /// The kernel address of this header tag.
+    pub address: u64,
+    pub identifier: u64,
+    /// The bytes returned by the translation callback for this tag, starting with the tag
+    /// header.
+    pub bytes: &'a [u8],
+}
+
+impl<'a> ParsedHeaderTag<'a> {
+    /// Returns the kind of this header tag.
+    pub fn kind(&self) -> HeaderTagKind {
+        HeaderTagKind::from_identifier(self.identifier)
+    }
+
+    /// Returns the bytes following the tag header, which contain the tag specific fields.
+    pub fn data(&self) -> &'a [u8] {
+        &self.bytes[core::mem::size_of::<StivaleTagHeader>()..]
+    }
+}
+
+/// Iterator over the header tags of a [ParsedHeader]. See [ParsedHeader::tags_iter] for more
+/// information.
+pub struct HeaderTagIter<'a, F>
+where
+    F: FnMut(u64) -> Option<&'a [u8]>,
+{
+    translate: F,
+    /// The kernel address of the header tag that we are about to yield.
+    next: u64,
+    /// The number of tags that may still be yielded before giving up on the chain.
+    remaining: usize,
+}
+
+impl<'a, F> Iterator for HeaderTagIter<'a, F>
+where
+    F: FnMut(u64) -> Option<&'a [u8]>,
+{
+    type Item = Result<ParsedHeaderTag<'a>, HeaderParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next == 0 || self.remaining == 0 {
+            return None;
+        }
+
+        let address = self.next;
+        // Stop the iteration after an error, since the rest of the chain is unreachable.
+        self.next = 0;
+        self.remaining -= 1;
+
+        let bytes = match (self.translate)(address) {
+            Some(bytes) => bytes,
+            None => return Some(Err(HeaderParseError::UnmappedAddress(address))),
+        };
+
+        let identifier = match read_u64(bytes, 0) {
+            Ok(identifier) => identifier,
+            Err(error) => return Some(Err(error)),
+        };
+
+        match read_u64(bytes, 8) {
+            Ok(next) => self.next = next,
+            Err(error) => return Some(Err(error)),
+        }
+
+        Some(Ok(ParsedHeaderTag {
+            address,
+            identifier,
+            bytes,
+        }))
+    }
+}
+
+/// Reads the little endian `u64` at the provided offset.
+fn read_u64(bytes: &[u8], offset: usize) -> Result<u64, HeaderParseError> {
+    let mut value = [0; 8];
+    value.copy_from_slice(
+        bytes
+            .get(offset..offset + 8)
+            .ok_or(HeaderParseError::TooShort)?,
+    );
+
+    Ok(u64::from_le_bytes(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::bytes_of;
+
+    extern "C" fn entry(_: &'static StivaleStruct) -> ! {
+        unreachable!()
+    }
+
+    #[test]
+    fn default_header() {
+        let (default, new) = (StivaleHeader::default(), StivaleHeader::new());
+
+        assert_eq!(unsafe { bytes_of(&default) }, unsafe { bytes_of(&new) });
+        assert!(default.get_stack().is_null());
+        assert_eq!(default.get_flags(), 0);
+        assert_eq!(unsafe { default.tags_chain_len() }, 0);
+    }
+
+    #[test]
+    fn parse_header() {
+        static STACK: [u8; 4096] = [0; 4096];
+        static FRAMEBUFFER: StivaleFramebufferHeaderTag =
+            StivaleFramebufferHeaderTag::new().framebuffer_bpp(24);
+        static UNMAP_NULL: StivaleUnmapNullHeaderTag = StivaleUnmapNullHeaderTag::new()
+            .next(&FRAMEBUFFER as *const StivaleFramebufferHeaderTag as *const ());
+
+        let header = StivaleHeader::new()
+            .entry_point(entry)
+            .stack(STACK.as_ptr())
+            .flags(0b11110)
+            .tags(&UNMAP_NULL as *const StivaleUnmapNullHeaderTag as *const ());
+        let parsed = parse(unsafe { bytes_of(&header) }).unwrap();
+
+        assert_eq!(parsed.entry_point, entry as *const () as u64);
+        assert_eq!(parsed.stack, STACK.as_ptr() as u64);
+        assert_eq!(parsed.flags, 0b11110);
+
+        let framebuffer_address = &FRAMEBUFFER as *const _ as u64;
+        let unmap_null_address = &UNMAP_NULL as *const _ as u64;
+        let mut tags = parsed.tags_iter(|address| unsafe {
+            if address == framebuffer_address {
+                Some(bytes_of(&FRAMEBUFFER))
+            } else if address == unmap_null_address {
+                Some(bytes_of(&UNMAP_NULL))
+            } else {
+                None
+            }
+        });
+
+        let unmap_null = tags.next().unwrap().unwrap();
+        assert_eq!(unmap_null.address, unmap_null_address);
+        assert_eq!(unmap_null.kind(), HeaderTagKind::UnmapNull);
+        assert!(unmap_null.data().is_empty());
+
+        let framebuffer = tags.next().unwrap().unwrap();
+        assert_eq!(framebuffer.kind(), HeaderTagKind::Framebuffer);
+        assert_eq!(framebuffer.data(), &[0, 0, 0, 0, 24, 0, 0, 0]);
+
+        assert!(tags.next().is_none());
+    }
+
+    #[test]
+    #[cfg(feature = "extra-traits")]
+    fn extra_traits() {
+        extern crate std;
+        use std::format;
+
+        let tag = StivaleFramebufferHeaderTag::new().framebuffer_bpp(32);
+        let copy = tag;
+        assert_eq!(copy, tag);
+        assert_ne!(tag, StivaleFramebufferHeaderTag::new());
+        assert!(format!("{:?}", tag).contains("framebuffer_bpp: 32"));
+
+        let header = StivaleHeader::new().flags(0b10);
+        assert!(format!("{:?}", header).contains("flags: 2"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(parse(&[0; 31]), Err(HeaderParseError::TooShort));
+
+        let mut bytes = [0; 32];
+        bytes[24..].copy_from_slice(&0xffffffff80001000u64.to_le_bytes());
+        let parsed = parse(&bytes).unwrap();
+
+        let mut tags = parsed.tags_iter(|_| None);
+        assert_eq!(
+            tags.next().unwrap().unwrap_err(),
+            HeaderParseError::UnmappedAddress(0xffffffff80001000)
+        );
+        assert!(tags.next().is_none());
+
+        let mut tags = parsed.tags_iter(|_| Some(&[0; 12]));
+        assert_eq!(
+            tags.next().unwrap().unwrap_err(),
+            HeaderParseError::TooShort
+        );
+        assert!(tags.next().is_none());
+    }
+
+    #[test]
+    fn unknown_header_tag() {
+        let mut tag = [0; 16];
+        tag[..8].copy_from_slice(&0x1234u64.to_le_bytes());
+
+        let mut bytes = [0; 32];
+        bytes[24..].copy_from_slice(&0x1000u64.to_le_bytes());
+        let parsed = parse(&bytes).unwrap();
+        let tag = parsed.tags_iter(|_| Some(&tag)).next().unwrap().unwrap();
+
+        assert_eq!(tag.kind(), HeaderTagKind::Unknown(0x1234));
+    }
+}
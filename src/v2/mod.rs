@@ -2,24 +2,48 @@
 //! modern version of the legacy stivale protocol which provides the kernel with most of the features
 //! one may need. The stivale2 protocol also supports 32-bit systems.
 
-use core::mem;
-
-mod header;
+use core::{fmt, mem};
+
+mod anchor;
+mod boot_info;
+#[cfg(feature = "alloc")]
+pub mod build;
+#[cfg(feature = "frame-alloc")]
+pub mod frame_alloc;
+pub mod header;
 mod tag;
-mod utils;
+pub mod tag_ids;
+pub mod utils;
+pub mod writer;
 
+pub use crate::common::{MaskInfo, MemoryRange};
+pub use anchor::*;
+pub use boot_info::*;
 pub use header::*;
 pub use tag::*;
 
+/// The maximum number of tags that are walked when traversing a tag chain. No bootloader passes
+/// anywhere near this many tags, so a longer chain is treated as corrupted (or cyclic), and every
+/// traversal of the chain is guaranteed to terminate.
+pub const TAG_COUNT_MAX: usize = 32;
+
+#[deprecated(note = "use TAG_COUNT_MAX instead")]
+pub const MAX_TAGS: usize = TAG_COUNT_MAX;
+
+/// The smallest UNIX epoch considered plausible by [StivaleStruct::timestamp], which corresponds
+/// to `2001-09-09 01:46:40 UTC`. Some bootloaders report an epoch of zero when the RTC is
+/// unavailable.
+pub const MIN_VALID_EPOCH: u64 = 1_000_000_000;
+
 #[repr(C)]
 pub struct StivaleStruct {
     bootloader_brand: [u8; 64],
     bootloader_version: [u8; 64],
-    tags: u64,
+    pub(crate) tags: u64,
 }
 
 impl StivaleStruct {
-    pub fn new() -> Self {
+    pub const fn new() -> Self {
         Self {
             bootloader_brand: [0; 64],
             bootloader_version: [0; 64],
@@ -27,6 +51,36 @@ impl StivaleStruct {
         }
     }
 
+    /// Returns a reference to the stivale2 structure at the provided address, after checking
+    /// that the address is non-null and 8-byte aligned and that the bootloader brand and version
+    /// are 0-terminated.
+    ///
+    /// ## Safety
+    /// If the address passes the alignment checks, it must point to at least
+    /// `size_of::<StivaleStruct>()` readable bytes which stay valid for the rest of the program,
+    /// since this cannot be checked without potentially faulting.
+    pub unsafe fn load_with_validation(address: usize) -> Result<&'static Self, LoadError> {
+        if address == 0 {
+            return Err(LoadError::NullAddress);
+        }
+
+        if !address.is_multiple_of(mem::align_of::<u64>()) {
+            return Err(LoadError::Misaligned);
+        }
+
+        let stivale_struct = &*(address as *const Self);
+
+        if !stivale_struct.bootloader_brand.contains(&0) {
+            return Err(LoadError::UnterminatedBrand);
+        }
+
+        if !stivale_struct.bootloader_version.contains(&0) {
+            return Err(LoadError::UnterminatedVersion);
+        }
+
+        Ok(stivale_struct)
+    }
+
     // SAFETY: Its allowed to update the tags, bootloader brand and bootloader version fields
     // since the stivale header provides an immutable reference to the stivale struct
     // and then the stivale struct is only allowed to be updated if its made by the user itself
@@ -51,178 +105,768 @@ impl StivaleStruct {
         utils::string_from_slice(&self.bootloader_version)
     }
 
+    /// Returns the bootloader brand, or `None` if it is not 0-terminated or not valid UTF-8.
+    /// Unlike [StivaleStruct::bootloader_brand], a malformed brand is never truncated.
+    pub fn try_bootloader_brand(&self) -> Option<&str> {
+        utils::try_string_from_slice(&self.bootloader_brand)
+    }
+
+    /// Returns the bootloader version, or `None` if it is not 0-terminated or not valid UTF-8.
+    /// Unlike [StivaleStruct::bootloader_version], a malformed version is never truncated.
+    pub fn try_bootloader_version(&self) -> Option<&str> {
+        utils::try_string_from_slice(&self.bootloader_version)
+    }
+
+    /// Returns the bootloader brand and version grouped together. Empty strings are reported as
+    /// `None`.
+    pub fn bootloader_info(&self) -> BootloaderInfo<'_> {
+        let brand = self.bootloader_brand();
+        let version = self.bootloader_version();
+
+        BootloaderInfo {
+            brand: (!brand.is_empty()).then_some(brand),
+            version: (!version.is_empty()).then_some(version),
+        }
+    }
+
+    /// Returns true if this structure and the first tag it points to are 8-byte aligned, as
+    /// required by their `u64` fields. A null tag pointer, meaning no tags were passed, is
+    /// accepted. This is a much cheaper check than [StivaleStruct::is_valid], and it does not
+    /// dereference the tag pointer.
+    pub fn validate_basic(&self) -> bool {
+        let align = mem::align_of::<u64>() as u64;
+
+        (self as *const Self as u64).is_multiple_of(align) && self.tags.is_multiple_of(align)
+    }
+
+    /// Returns true if the structure looks plausibly valid. The following is checked:
+    ///
+    /// - The bootloader brand and version are 0-terminated.
+    /// - The tag chain ends within [TAG_COUNT_MAX] tags, which also rules out cycles.
+    /// - Every tag in the chain has a non-zero identifier.
+    ///
+    /// The stivale2 protocol has no checksum, so this is only a heuristic which catches the most
+    /// obvious corruption. The checks are only performed in debug builds or when the `validate`
+    /// feature is enabled; otherwise this function always returns true and compiles down to
+    /// nothing.
+    pub fn is_valid(&self) -> bool {
+        if !cfg!(any(debug_assertions, feature = "validate")) {
+            return true;
+        }
+
+        if !self.bootloader_brand.contains(&0) || !self.bootloader_version.contains(&0) {
+            return false;
+        }
+
+        let mut current_tag = match utils::try_into_usize(self.tags) {
+            Some(address) => address as *const StivaleTagHeader,
+            None => return false,
+        };
+
+        for _ in 0..TAG_COUNT_MAX {
+            if current_tag.is_null() {
+                return true;
+            }
+
+            let tag = unsafe { &*current_tag };
+
+            if tag.identifier == 0 {
+                return false;
+            }
+
+            current_tag = match utils::try_into_usize(tag.next) {
+                Some(address) => address as *const StivaleTagHeader,
+                None => return false,
+            };
+        }
+
+        current_tag.is_null()
+    }
+
+    /// Returns the address of the first tag with the provided identifier. The search stops after
+    /// [TAG_COUNT_MAX] tags, or at the first tag whose address does not fit in a pointer, which
+    /// can only happen on 32-bit targets.
     pub fn get_tag(&self, identifier: u64) -> Option<u64> {
-        let mut current_tag = self.tags as *const StivaleTagHeader;
+        let mut current_tag = utils::try_into_usize(self.tags)? as *const StivaleTagHeader;
+
+        for _ in 0..TAG_COUNT_MAX {
+            if current_tag.is_null() {
+                return None;
+            }
 
-        while !current_tag.is_null() {
             let tag = unsafe { &*current_tag };
 
             if tag.identifier == identifier {
                 return Some(current_tag as u64);
             }
 
-            current_tag = tag.next as *const StivaleTagHeader;
+            current_tag = utils::try_into_usize(tag.next)? as *const StivaleTagHeader;
         }
 
         None
     }
 
+    /// Returns the number of tags in the tag chain, counting up to [TAG_COUNT_MAX] tags. The
+    /// count stops at the first tag whose address does not fit in a pointer.
+    pub fn tag_chain_length(&self) -> usize {
+        let mut address = self.tags;
+        let mut length = 0;
+
+        while length < TAG_COUNT_MAX {
+            let current_tag = match utils::try_into_usize(address) {
+                Some(0) | None => break,
+                Some(address) => address as *const StivaleTagHeader,
+            };
+
+            address = unsafe { (*current_tag).next };
+            length += 1;
+        }
+
+        length
+    }
+
     pub fn command_line(&self) -> Option<&'static StivaleCommandLineTag> {
-        self.get_tag(0xe5e76a1b4597a781)
+        self.get_tag(tag_ids::COMMAND_LINE)
             .map(|addr| unsafe { &*(addr as *const StivaleCommandLineTag) })
     }
 
     pub fn memory_map(&self) -> Option<&'static StivaleMemoryMapTag> {
-        self.get_tag(0x2187f79e8612de07).map(|addr| {
-            let ptr = addr as *mut u8;
-            unsafe {
-                let count = *(ptr.add(mem::size_of::<StivaleTagHeader>()) as *const u64);
-                let memory_map_ptr = StivaleMemoryMapTag::new_from_ptr_count(ptr as *mut (), count);
-                &*memory_map_ptr
-            }
-        })
+        self.counted_tag(tag_ids::MEMORY_MAP)
+            .map(|(ptr, count)| unsafe { &*StivaleMemoryMapTag::new_from_ptr_count(ptr, count) })
     }
 
     pub fn framebuffer(&self) -> Option<&'static StivaleFramebufferTag> {
-        self.get_tag(0x506461d2950408fa)
+        self.get_tag(tag_ids::FRAMEBUFFER)
             .map(|addr| unsafe { &*(addr as *const StivaleFramebufferTag) })
     }
 
     pub fn edid_info(&self) -> Option<&'static StivaleEdidInfoTag> {
-        self.get_tag(0x968609d7af96b845).map(|addr| {
-            let ptr = addr as *mut u8;
-            unsafe {
-                let count = *(ptr.add(mem::size_of::<StivaleTagHeader>()) as *const u64);
-                let edid_ptr = StivaleEdidInfoTag::new_from_ptr_count(ptr as *mut (), count);
-                &*edid_ptr
-            }
-        })
+        self.counted_tag(tag_ids::EDID_INFO)
+            .map(|(ptr, count)| unsafe { &*StivaleEdidInfoTag::new_from_ptr_count(ptr, count) })
     }
 
     #[allow(deprecated)]
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
     pub fn mtrr(&self) -> Option<&'static StivaleMtrrTag> {
-        self.get_tag(0x6bc1a78ebe871172)
+        self.get_tag(tag_ids::MTRR)
             .map(|addr| unsafe { &*(addr as *const StivaleMtrrTag) })
     }
 
     pub fn terminal(&self) -> Option<&'static StivaleTerminalTag> {
-        self.get_tag(0xc2b3f4c3233b0974)
+        self.get_tag(tag_ids::TERMINAL)
             .map(|addr| unsafe { &*(addr as *const StivaleTerminalTag) })
     }
 
     pub fn modules(&self) -> Option<&'static StivaleModuleTag> {
-        self.get_tag(0x4b6fe466aade04ce).map(|addr| {
-            let ptr = addr as *mut u8;
-            unsafe {
-                let count = *(ptr.add(mem::size_of::<StivaleTagHeader>()) as *const u64);
-                let module_ptr = StivaleModuleTag::new_from_ptr_count(ptr as *mut (), count);
-                &*module_ptr
-            }
-        })
+        self.counted_tag(tag_ids::MODULES)
+            .map(|(ptr, count)| unsafe { &*StivaleModuleTag::new_from_ptr_count(ptr, count) })
     }
 
     pub fn rsdp(&self) -> Option<&'static StivaleRsdpTag> {
-        self.get_tag(0x9e1786930a375e78)
+        self.get_tag(tag_ids::RSDP)
             .map(|addr| unsafe { &*(addr as *const StivaleRsdpTag) })
     }
 
     pub fn smbios(&self) -> Option<&'static StivaleSmbiosTag> {
-        self.get_tag(0x274bd246c62bf7d1)
+        self.get_tag(tag_ids::SMBIOS)
             .map(|addr| unsafe { &*(addr as *const StivaleSmbiosTag) })
     }
 
     pub fn epoch(&self) -> Option<&'static StivaleEpochTag> {
-        self.get_tag(0x566a7bed888e1407)
+        self.get_tag(tag_ids::EPOCH)
             .map(|addr| unsafe { &*(addr as *const StivaleEpochTag) })
     }
 
+    /// Returns the UNIX epoch at boot if the epoch tag is present and the epoch is plausible, that
+    /// is, greater than [MIN_VALID_EPOCH].
+    pub fn timestamp(&self) -> Option<u64> {
+        self.epoch()
+            .map(|tag| tag.epoch)
+            .filter(|&epoch| epoch > MIN_VALID_EPOCH)
+    }
+
+    /// Returns the UNIX epoch at boot, or `fallback` if no plausible epoch is available. See
+    /// [StivaleStruct::timestamp] for more information.
+    pub fn timestamp_or_default(&self, fallback: u64) -> u64 {
+        self.timestamp().unwrap_or(fallback)
+    }
+
     pub fn firmware(&self) -> Option<&'static StivaleFirmwareTag> {
-        self.get_tag(0x359d837855e3858c)
+        self.get_tag(tag_ids::FIRMWARE)
             .map(|addr| unsafe { &*(addr as *const StivaleFirmwareTag) })
     }
 
     pub fn efi_system_table(&self) -> Option<&'static StivaleEfiSystemTableTag> {
-        self.get_tag(0x4bc5ec15845b558e)
+        self.get_tag(tag_ids::EFI_SYSTEM_TABLE)
             .map(|addr| unsafe { &*(addr as *const StivaleEfiSystemTableTag) })
     }
 
     pub fn kernel_file(&self) -> Option<&'static StivaleKernelFileTag> {
-        self.get_tag(0xe599d90c2975584a)
+        self.get_tag(tag_ids::KERNEL_FILE)
             .map(|addr| unsafe { &*(addr as *const StivaleKernelFileTag) })
     }
 
     pub fn kernel_slide(&self) -> Option<&'static StivaleKernelSlideTag> {
-        self.get_tag(0xee80847d01506c57)
+        self.get_tag(tag_ids::KERNEL_SLIDE)
             .map(|addr| unsafe { &*(addr as *const StivaleKernelSlideTag) })
     }
 
     pub fn smp(&self) -> Option<&'static StivaleSmpTag> {
-        self.get_tag(0x34d1d96339647025).map(|addr| {
+        self.smp_ptr().map(|smp_ptr| unsafe { &*smp_ptr })
+    }
+
+    pub fn smp_mut(&mut self) -> Option<&'static mut StivaleSmpTag> {
+        self.smp_ptr().map(|smp_ptr| unsafe { &mut *smp_ptr })
+    }
+
+    /// Returns the address of the tag with the provided identifier along with the count stored
+    /// right after its header, for the tags with a DST tail. A reference to such a tag is a fat
+    /// pointer which carries the length of the tail, and `get_tag` only yields the address of the
+    /// tag, so the fat pointer is rebuilt from the count with `new_from_ptr_count`.
+    fn counted_tag(&self, identifier: u64) -> Option<(*mut (), u64)> {
+        self.get_tag(identifier).map(|addr| {
             let ptr = addr as *mut u8;
-            unsafe {
-                // +32 calculated from the definition of the struct, offset to the cpu_count
-                let count = *(ptr.add(32) as *const u64);
-                let smp_ptr = StivaleSmpTag::new_from_ptr_count(ptr as *mut (), count);
-                &*smp_ptr
-            }
+            let count = unsafe { *(ptr.add(mem::size_of::<StivaleTagHeader>()) as *const u64) };
+
+            (ptr as *mut (), count)
         })
     }
 
-    pub fn smp_mut(&mut self) -> Option<&'static mut StivaleSmpTag> {
-        self.get_tag(0x34d1d96339647025).map(|addr| {
+    /// Returns a fat pointer to the SMP tag, if present. `StivaleSmpTag` has a DST tail, so the
+    /// length of the `smp_info_array` is read from the `cpu_count` field, which follows the tag
+    /// header, the flags and the two 32-bit `bsp_lapic_id` and `unused` fields.
+    fn smp_ptr(&self) -> Option<*mut StivaleSmpTag> {
+        const CPU_COUNT_OFFSET: usize = mem::size_of::<StivaleTagHeader>()
+            + mem::size_of::<StivaleSmpHeaderTagFlags>()
+            + 2 * mem::size_of::<u32>();
+
+        self.get_tag(tag_ids::SMP).map(|addr| {
             let ptr = addr as *mut u8;
             unsafe {
-                // +32 calculated from the definition of the struct, offset to the cpu_count
-                let count = *(ptr.add(32) as *const u64);
-                let smp_ptr = StivaleSmpTag::new_from_ptr_count(ptr as *mut (), count);
-                &mut *smp_ptr
+                let count = *(ptr.add(CPU_COUNT_OFFSET) as *const u64);
+                StivaleSmpTag::new_from_ptr_count(ptr as *mut (), count)
             }
         })
     }
 
+    /// Returns the console to use for early output. The terminal is preferred if the terminal
+    /// tag is present, otherwise the memory mapped UART port is used if the UART tag is present.
+    ///
+    /// ## Example
+    /// ```rust,no_run
+    /// use core::fmt::Write;
+    /// use stivale_boot::v2::StivaleStruct;
+    ///
+    /// fn kmain(stivale_struct: &'static StivaleStruct) {
+    ///     if let Some(mut console) = stivale_struct.terminal_or_uart() {
+    ///         writeln!(console, "Hello, {}!", "Stivale").unwrap();
+    ///     }
+    /// }
+    /// ```
+    pub fn terminal_or_uart(&self) -> Option<EarlyConsole<'static>> {
+        self.terminal()
+            .map(EarlyConsole::Terminal)
+            .or_else(|| self.uart().map(EarlyConsole::Uart))
+    }
+
     pub fn pxe_info(&self) -> Option<&'static StivalePxeInfoTag> {
-        self.get_tag(0x29d1e96239247032)
+        self.get_tag(tag_ids::PXE_INFO)
             .map(|addr| unsafe { &*(addr as *const StivalePxeInfoTag) })
     }
 
     pub fn uart(&self) -> Option<&'static StivaleUartTag> {
-        self.get_tag(0xb813f9b8dbc78797)
+        self.get_tag(tag_ids::UART)
             .map(|addr| unsafe { &*(addr as *const StivaleUartTag) })
     }
 
     pub fn dev_tree(&self) -> Option<&'static StivaleDeviceTreeTag> {
-        self.get_tag(0xabb29bd49a2833fa)
+        self.get_tag(tag_ids::DEVICE_TREE)
             .map(|addr| unsafe { &*(addr as *const StivaleDeviceTreeTag) })
     }
 
     pub fn vmap(&self) -> Option<&'static StivaleVMapTag> {
-        self.get_tag(0xb0ed257db18cb58f)
+        self.get_tag(tag_ids::VMAP)
             .map(|addr| unsafe { &*(addr as *const StivaleVMapTag) })
     }
 
     pub fn kernel_file_v2(&self) -> Option<&'static StivaleKernelFileV2Tag> {
-        self.get_tag(0x37c13018a02c6ea2)
+        self.get_tag(tag_ids::KERNEL_FILE_V2)
             .map(|addr| unsafe { &*(addr as *const StivaleKernelFileV2Tag) })
     }
 
+    /// Returns the protected memory ranges (PMRs) tag, if present.
     pub fn pmrs(&self) -> Option<&'static StivalePmrsTag> {
-        self.get_tag(0x5df266a64047b6bd).map(|addr| {
-            let ptr = addr as *mut u8;
-            unsafe {
-                let count = *(ptr.add(mem::size_of::<StivaleTagHeader>()) as *const u64);
-                let pmrs_ptr = StivalePmrsTag::new_from_ptr_count(ptr as *mut (), count);
-                &*pmrs_ptr
-            }
-        })
+        self.counted_tag(tag_ids::PMRS)
+            .map(|(ptr, count)| unsafe { &*StivalePmrsTag::new_from_ptr_count(ptr, count) })
     }
 
     pub fn kernel_base_addr(&self) -> Option<&'static StivaleKernelBaseAddressTag> {
-        self.get_tag(0x060d78874a2a8af0)
+        self.get_tag(tag_ids::KERNEL_BASE_ADDRESS)
             .map(|addr| unsafe { &*(addr as *const StivaleKernelBaseAddressTag) })
     }
 
     pub fn boot_volume(&self) -> Option<&'static StivaleBootVolumeTag> {
-        self.get_tag(0x9b4358364c19ee62)
+        self.get_tag(tag_ids::BOOT_VOLUME)
             .map(|addr| unsafe { &*(addr as *const StivaleBootVolumeTag) })
     }
+
+    /// Returns the memory map tag, panicking with the provided message if the bootloader did not
+    /// provide one.
+    #[track_caller]
+    pub fn memory_map_or_panic(&self, msg: &'static str) -> &'static StivaleMemoryMapTag {
+        self.memory_map().unwrap_or_else(|| panic!("{}", msg))
+    }
+
+    /// Returns the memory map tag, panicking if the bootloader did not provide one.
+    #[track_caller]
+    pub fn require_memory_map(&self) -> &'static StivaleMemoryMapTag {
+        self.memory_map_or_panic("stivale2: the bootloader did not provide a memory map")
+    }
+
+    /// Returns the framebuffer tag, panicking if the bootloader did not provide one.
+    #[track_caller]
+    pub fn require_framebuffer(&self) -> &'static StivaleFramebufferTag {
+        self.framebuffer()
+            .expect("stivale2: the bootloader did not provide a framebuffer")
+    }
+
+    /// Returns the terminal tag, panicking if the bootloader did not provide one.
+    #[track_caller]
+    pub fn require_terminal(&self) -> &'static StivaleTerminalTag {
+        self.terminal()
+            .expect("stivale2: the bootloader did not provide a terminal")
+    }
+
+    /// Returns the SMP tag, panicking if the bootloader did not provide one.
+    #[track_caller]
+    pub fn require_smp(&self) -> &'static StivaleSmpTag {
+        self.smp()
+            .expect("stivale2: the bootloader did not provide SMP information")
+    }
+}
+
+impl Default for StivaleStruct {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for StivaleStruct {
+    /// Formats the bootloader brand and version, along with the name of every tag in the tag
+    /// chain. Unknown tags are printed as their raw identifier.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StivaleStruct")
+            .field("bootloader_brand", &self.bootloader_brand())
+            .field("bootloader_version", &self.bootloader_version())
+            .field("tags", &DebugTags(self))
+            .finish()
+    }
+}
+
+/// The identification of the bootloader which booted the kernel, as returned by
+/// [StivaleStruct::bootloader_info]. It is displayed as `Brand vVersion`, which is handy for boot
+/// logs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BootloaderInfo<'a> {
+    /// The bootloader brand, for example `Limine`.
+    pub brand: Option<&'a str>,
+    /// The bootloader version, for example `2.0`.
+    pub version: Option<&'a str>,
+}
+
+impl fmt::Display for BootloaderInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.brand.unwrap_or("unknown bootloader"))?;
+
+        if let Some(version) = self.version {
+            write!(f, " v{}", version)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Error returned by [StivaleStruct::load_with_validation].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LoadError {
+    /// The address of the structure is zero.
+    NullAddress,
+    /// The address of the structure is not 8-byte aligned.
+    Misaligned,
+    /// The bootloader brand is not 0-terminated within its 64 bytes.
+    UnterminatedBrand,
+    /// The bootloader version is not 0-terminated within its 64 bytes.
+    UnterminatedVersion,
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::NullAddress => f.write_str("stivale2 structure address is null"),
+            LoadError::Misaligned => f.write_str("stivale2 structure address not 8-byte aligned"),
+            LoadError::UnterminatedBrand => f.write_str("bootloader brand not 0-terminated"),
+            LoadError::UnterminatedVersion => f.write_str("bootloader version not 0-terminated"),
+        }
+    }
+}
+
+/// Helper to format the tags of a [StivaleStruct] as a list.
+struct DebugTags<'a>(&'a StivaleStruct);
+
+impl fmt::Debug for DebugTags<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut list = f.debug_list();
+        let mut address = self.0.tags;
+
+        for _ in 0..TAG_COUNT_MAX {
+            let current_tag = match utils::try_into_usize(address) {
+                Some(0) => return list.finish(),
+                Some(address) => address as *const StivaleTagHeader,
+                None => break,
+            };
+
+            let tag = unsafe { &*current_tag };
+            list.entry(&DebugTagName(tag.identifier));
+
+            address = tag.next;
+        }
+
+        list.finish_non_exhaustive()
+    }
+}
+
+/// Helper to format a tag identifier as its name, or its raw value if the tag is unknown.
+struct DebugTagName(u64);
+
+impl fmt::Debug for DebugTagName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match tag_ids::name(self.0) {
+            Some(name) => f.write_str(name),
+            None => write!(f, "{:#018x}", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::test_utils::v2::{chain, raw_dst_tag};
+    use std::{string::ToString, vec};
+
+    #[test]
+    fn tag_chain_limit() {
+        let mut tags = [
+            raw_dst_tag(tag_ids::COMMAND_LINE, 2, 0, 0),
+            raw_dst_tag(tag_ids::EPOCH, 2, 0, 0),
+        ];
+        let stivale_struct = chain(&mut tags);
+
+        assert_eq!(stivale_struct.tag_chain_length(), 2);
+        assert_eq!(StivaleStruct::new().tag_chain_length(), 0);
+
+        // Make the chain cyclic, the traversals must still terminate.
+        tags[1][1] = tags[0].as_ptr() as u64;
+
+        assert_eq!(stivale_struct.tag_chain_length(), TAG_COUNT_MAX);
+        assert_eq!(
+            stivale_struct.get_tag(tag_ids::EPOCH),
+            Some(tags[1].as_ptr() as u64)
+        );
+        assert_eq!(stivale_struct.get_tag(tag_ids::SMP), None);
+    }
+
+    #[test]
+    fn dst_tag_accessors() {
+        let mut memory_map = raw_dst_tag(tag_ids::MEMORY_MAP, 2, 2, 3);
+        memory_map[3..9].copy_from_slice(&[0x1000, 0x9f000, 1, 0x100000, 0x100000, 0x1001]);
+
+        let mut modules = raw_dst_tag(tag_ids::MODULES, 2, 1, 18);
+        modules[3..5].copy_from_slice(&[0x200000, 0x201000]);
+        modules[5] = u64::from_le_bytes(*b"initrd\0\0");
+
+        let mut smp = raw_dst_tag(tag_ids::SMP, 4, 3, 4);
+        smp[5 + 2 * 4] = 2 | 3 << 32;
+
+        let mut pmrs = raw_dst_tag(tag_ids::PMRS, 2, 2, 3);
+        pmrs[6] = 0xffffffff80001000;
+
+        // The EDID tag stores its length in bytes rather than words.
+        let mut edid = raw_dst_tag(tag_ids::EDID_INFO, 2, 1, 1);
+        edid[2] = 8;
+        edid[3] = 0x00ffffffffffff00;
+
+        let mut tags = [memory_map, modules, smp, pmrs, edid];
+        let stivale_struct = chain(&mut tags);
+
+        let memory_map = stivale_struct.memory_map().unwrap();
+        assert_eq!(memory_map.as_slice().len(), 2);
+        assert_eq!(memory_map.as_slice()[1].base, 0x100000);
+        assert_eq!(
+            memory_map.as_slice()[1].entry_type,
+            StivaleMemoryMapEntryType::Kernel
+        );
+
+        let modules = stivale_struct.modules().unwrap();
+        assert_eq!(modules.as_slice().len(), 1);
+        assert_eq!(modules.as_slice()[0].as_str(), "initrd");
+        assert_eq!(modules.as_slice()[0].size(), 0x1000);
+
+        let smp = stivale_struct.smp().unwrap();
+        assert_eq!(smp.as_slice().len(), 3);
+        assert_eq!(smp.as_slice()[2].acpi_processor_uid, 2);
+        assert_eq!(smp.as_slice()[2].lapic_id, 3);
+
+        let pmrs = stivale_struct.pmrs().unwrap();
+        assert_eq!(pmrs.as_slice().len(), 2);
+        assert_eq!(pmrs.as_slice()[1].base, 0xffffffff80001000);
+
+        let edid = stivale_struct.edid_info().unwrap();
+        assert_eq!(
+            edid.as_slice(),
+            &[0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00]
+        );
+    }
+
+    #[test]
+    fn modules_count() {
+        let mut modules = raw_dst_tag(tag_ids::MODULES, 2, 2, 18);
+        modules[3..5].copy_from_slice(&[0x200000, 0x201000]);
+        modules[5] = u64::from_le_bytes(*b"initrd\0\0");
+        modules[21..23].copy_from_slice(&[0x300000, 0x304000]);
+        modules[23] = u64::from_le_bytes(*b"font.psf");
+
+        let mut tags = [modules];
+        let stivale_struct = chain(&mut tags);
+
+        let modules = stivale_struct.modules().unwrap();
+        assert_eq!(modules.iter().count(), 2);
+        assert_eq!(modules.as_slice()[1].as_str(), "font.psf");
+        assert_eq!(modules.as_slice()[1].size(), 0x4000);
+    }
+
+    #[test]
+    fn smp_cpu_count() {
+        let mut smp = vec![0u64; 5 + 3 * 4];
+
+        unsafe {
+            let tag = &mut *StivaleSmpTag::init_header(
+                smp.as_mut_ptr() as *mut (),
+                StivaleSmpHeaderTagFlags::X2APIC,
+                7,
+                3,
+            );
+            tag.unused = 0xdead;
+            tag.smp_info_array[2].lapic_id = 7;
+        }
+
+        let mut tags = [smp];
+        let mut stivale_struct = chain(&mut tags);
+
+        let smp = stivale_struct.smp().unwrap();
+        assert_eq!(smp.cpu_count(), 3);
+        assert_eq!(smp.as_slice().len(), 3);
+        assert_eq!(smp.bsp_lapic_id, 7);
+        assert_eq!(smp.as_slice()[2].lapic_id, 7);
+
+        assert_eq!(stivale_struct.smp_mut().unwrap().smp_info_array.len(), 3);
+    }
+
+    #[test]
+    fn require_present_tag() {
+        let mut tags = [raw_dst_tag(tag_ids::MEMORY_MAP, 2, 0, 3)];
+        let stivale_struct = chain(&mut tags);
+
+        assert_eq!(stivale_struct.require_memory_map().as_slice().len(), 0);
+        assert_eq!(
+            stivale_struct
+                .memory_map_or_panic("no memory map")
+                .as_slice()
+                .len(),
+            0
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "no memory map")]
+    fn memory_map_or_panic_missing() {
+        StivaleStruct::new().memory_map_or_panic("no memory map");
+    }
+
+    #[test]
+    #[should_panic(expected = "did not provide SMP information")]
+    fn require_smp_missing() {
+        StivaleStruct::new().require_smp();
+    }
+
+    fn stivale_struct(brand: &[u8], version: &[u8]) -> StivaleStruct {
+        let mut stivale_struct = StivaleStruct::new();
+        stivale_struct.bootloader_brand[..brand.len()].copy_from_slice(brand);
+        stivale_struct.bootloader_version[..version.len()].copy_from_slice(version);
+        stivale_struct
+    }
+
+    #[test]
+    fn header_layout() {
+        assert_eq!(mem::size_of::<StivaleHeader>(), 32);
+        assert_eq!(mem::size_of::<StivaleUnmapNullHeaderTag>(), 16);
+        assert_eq!(mem::size_of::<StivaleFramebufferHeaderTag>(), 24);
+    }
+
+    #[test]
+    fn try_into_usize() {
+        assert_eq!(utils::try_into_usize(0xb8000), Some(0xb8000));
+        assert_eq!(
+            utils::try_into_usize(u32::MAX as u64),
+            Some(u32::MAX as usize)
+        );
+
+        if cfg!(target_pointer_width = "32") {
+            assert_eq!(utils::try_into_usize(1 << 32), None);
+            assert_eq!(utils::try_into_usize(0xffffffff80000000), None);
+        } else {
+            assert_eq!(utils::try_into_usize(1 << 32), Some(1 << 32));
+        }
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "32")]
+    fn tags_above_4gib() {
+        let mut stivale_struct = StivaleStruct::new();
+        stivale_struct.tags = 1 << 32;
+
+        assert_eq!(stivale_struct.get_tag(tag_ids::MEMORY_MAP), None);
+        assert!(!stivale_struct.is_valid());
+    }
+
+    #[test]
+    fn load_with_validation() {
+        let valid = stivale_struct(b"Limine", b"2.0");
+        let address = &valid as *const StivaleStruct as usize;
+        let loaded = unsafe { StivaleStruct::load_with_validation(address) }.unwrap();
+        assert_eq!(loaded.bootloader_brand(), "Limine");
+
+        let load = |stivale_struct: &StivaleStruct| unsafe {
+            StivaleStruct::load_with_validation(stivale_struct as *const StivaleStruct as usize)
+        };
+
+        assert_eq!(
+            unsafe { StivaleStruct::load_with_validation(0) }.unwrap_err(),
+            LoadError::NullAddress
+        );
+        assert_eq!(
+            unsafe { StivaleStruct::load_with_validation(address + 4) }.unwrap_err(),
+            LoadError::Misaligned
+        );
+        assert_eq!(
+            load(&stivale_struct(&[b'a'; 64], b"2.0")).unwrap_err(),
+            LoadError::UnterminatedBrand
+        );
+        assert_eq!(
+            load(&stivale_struct(b"Limine", &[b'1'; 64])).unwrap_err(),
+            LoadError::UnterminatedVersion
+        );
+    }
+
+    #[test]
+    fn try_bootloader_strings() {
+        let invalid_version = stivale_struct(b"Limine", b"2.\xff");
+        assert_eq!(invalid_version.try_bootloader_brand(), Some("Limine"));
+        assert_eq!(invalid_version.try_bootloader_version(), None);
+        assert_eq!(invalid_version.bootloader_version(), "2.");
+
+        let unterminated_brand = stivale_struct(&[b'a'; 64], b"");
+        assert_eq!(unterminated_brand.try_bootloader_brand(), None);
+        assert_eq!(unterminated_brand.try_bootloader_version(), Some(""));
+    }
+
+    #[test]
+    fn bootloader_info_display() {
+        let stivale_struct = stivale_struct(b"Limine", b"2.0");
+        let info = stivale_struct.bootloader_info();
+
+        assert_eq!(info.brand, Some("Limine"));
+        assert_eq!(info.version, Some("2.0"));
+        assert_eq!(info.to_string(), "Limine v2.0");
+    }
+
+    #[test]
+    fn bootloader_info_missing_fields() {
+        assert_eq!(
+            stivale_struct(b"Limine", b"").bootloader_info().to_string(),
+            "Limine"
+        );
+        assert_eq!(
+            stivale_struct(b"", b"").bootloader_info(),
+            BootloaderInfo {
+                brand: None,
+                version: None,
+            }
+        );
+    }
+
+    /// Smoke tests for architectures other than x86, such as aarch64.
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+    mod non_x86 {
+        /// Stand-ins for the x86 only items. Referring to one of them is ambiguous, and thus fails
+        /// to compile, if the glob import of the crate provides an item of the same name.
+        mod absent {
+            pub struct StivaleMtrrHeaderTag;
+            pub struct Stivale5LevelPagingHeaderTag;
+            pub struct StivaleMtrrTag;
+
+            pub trait Mtrr {
+                fn mtrr(&self) {}
+            }
+
+            impl Mtrr for crate::v2::StivaleStruct {}
+        }
+
+        use crate::v2::writer::StructWriter;
+        use crate::v2::*;
+        use absent::*;
+
+        #[test]
+        fn x86_only_items_are_absent() {
+            let _: Option<(
+                StivaleMtrrHeaderTag,
+                Stivale5LevelPagingHeaderTag,
+                StivaleMtrrTag,
+            )> = None;
+            // Only resolves to the stand-in if there is no inherent `mtrr` method.
+            let () = StivaleStruct::new().mtrr();
+        }
+
+        #[test]
+        fn parse_synthetic_struct() {
+            #[repr(C, align(8))]
+            struct Buffer([u8; 512]);
+
+            let mut buffer = Buffer([0; 512]);
+            let mut writer = StructWriter::new(&mut buffer.0, "Limine", "2.0").unwrap();
+            writer
+                .push_memory_map(&[StivaleMemoryMapEntry::new(
+                    0x1000,
+                    0x9f000,
+                    StivaleMemoryMapEntryType::Usable,
+                )])
+                .unwrap();
+            writer.push_raw_tag(tag_ids::MTRR, &[]).unwrap();
+
+            let address = writer.struct_address() as usize;
+            let stivale_struct = unsafe { StivaleStruct::load_with_validation(address) }.unwrap();
+
+            assert_eq!(stivale_struct.bootloader_brand(), "Limine");
+            assert_eq!(
+                stivale_struct
+                    .memory_map()
+                    .map(|map| map.as_slice()[0].length),
+                Some(0x9f000)
+            );
+            // The tag of a x86 bootloader is still reachable through the raw lookup.
+            assert!(stivale_struct.get_tag(tag_ids::MTRR).is_some());
+        }
+    }
 }
@@ -1,16 +1,40 @@
+use core::fmt::Write;
+use core::iter::{FilterMap, FusedIterator};
 use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU64, Ordering};
 
 use super::header::StivaleSmpHeaderTagFlags;
+use super::tag_ids;
+use super::utils::ArrayWriter;
+use crate::common::{FrameWalker, MaskInfo, MemoryRange};
 
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivaleTagHeader {
     pub identifier: u64,
     pub next: u64,
 }
 
+impl StivaleTagHeader {
+    /// Creates a tag header with the provided identifier, which is not linked to any other tag.
+    pub const fn new(identifier: u64) -> Self {
+        Self {
+            identifier,
+            next: 0,
+        }
+    }
+}
+
 /// If the framebuffer tag was requested through the framebuffer tag header and its supported by the stivale
 /// bootloader, this tag is returned to the kernel. This tag provides an interface to the framebuffer.
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivaleFramebufferTag {
     pub header: StivaleTagHeader,
     /// The address of the framebuffer.
@@ -42,17 +66,288 @@ pub struct StivaleFramebufferTag {
 }
 
 impl StivaleFramebufferTag {
-    /// Returns the size of the framebuffer.
+    /// Creates a framebuffer tag using the RGB memory model, which is not linked to any other
+    /// tag.
+    #[allow(clippy::too_many_arguments)]
+    pub const fn new(
+        framebuffer_addr: u64,
+        width: u16,
+        height: u16,
+        pitch: u16,
+        bpp: u16,
+        red_mask: MaskInfo,
+        green_mask: MaskInfo,
+        blue_mask: MaskInfo,
+    ) -> Self {
+        Self {
+            header: StivaleTagHeader::new(tag_ids::FRAMEBUFFER),
+            framebuffer_addr,
+            framebuffer_width: width,
+            framebuffer_height: height,
+            framebuffer_pitch: pitch,
+            framebuffer_bpp: bpp,
+            memory_model: 1,
+            red_mask_size: red_mask.size,
+            red_mask_shift: red_mask.shift,
+            green_mask_size: green_mask.size,
+            green_mask_shift: green_mask.shift,
+            blue_mask_size: blue_mask.size,
+            blue_mask_shift: blue_mask.shift,
+            _padding: 0,
+        }
+    }
+
+    /// Returns the size of the framebuffer in bytes. The pitch is already in bytes, so this is
+    /// the pitch times the height.
     pub fn size(&self) -> usize {
+        self.pitch_usize() * self.height_usize()
+    }
+
+    /// Returns the width of the framebuffer in pixels as an `usize`.
+    #[inline]
+    pub fn width_usize(&self) -> usize {
+        self.framebuffer_width as usize
+    }
+
+    /// Returns the height of the framebuffer in pixels as an `usize`.
+    #[inline]
+    pub fn height_usize(&self) -> usize {
+        self.framebuffer_height as usize
+    }
+
+    /// Returns the pitch of the framebuffer in bytes as an `usize`.
+    #[inline]
+    pub fn pitch_usize(&self) -> usize {
         self.framebuffer_pitch as usize
-            * self.framebuffer_height as usize
-            * (self.framebuffer_bpp as usize / 8)
     }
+
+    /// Returns the bits per pixel of the framebuffer as an `usize`.
+    #[inline]
+    pub fn bpp_usize(&self) -> usize {
+        self.framebuffer_bpp as usize
+    }
+
+    /// Returns the number of bytes per pixel of the framebuffer.
+    #[inline]
+    pub fn bytes_per_pixel(&self) -> usize {
+        self.bpp_usize() / 8
+    }
+
+    /// Returns the size and shift of the red mask.
+    pub fn red_mask(&self) -> MaskInfo {
+        MaskInfo {
+            size: self.red_mask_size,
+            shift: self.red_mask_shift,
+        }
+    }
+
+    /// Returns the size and shift of the green mask.
+    pub fn green_mask(&self) -> MaskInfo {
+        MaskInfo {
+            size: self.green_mask_size,
+            shift: self.green_mask_shift,
+        }
+    }
+
+    /// Returns the size and shift of the blue mask.
+    pub fn blue_mask(&self) -> MaskInfo {
+        MaskInfo {
+            size: self.blue_mask_size,
+            shift: self.blue_mask_shift,
+        }
+    }
+
+    /// Returns the aspect ratio of the framebuffer as a reduced fraction, for example `(16, 9)`
+    /// for a 1920x1080 framebuffer. Returns `None` if either dimension is zero.
+    pub fn aspect_ratio(&self) -> Option<(u16, u16)> {
+        if self.framebuffer_width == 0 || self.framebuffer_height == 0 {
+            return None;
+        }
+
+        let gcd = super::utils::gcd(self.framebuffer_width, self.framebuffer_height);
+        Some((self.framebuffer_width / gcd, self.framebuffer_height / gcd))
+    }
+
+    /// Formats the mode of the framebuffer as `{width}x{height}@{bpp}bpp, pitch={pitch}` into
+    /// the provided buffer, without allocating.
+    pub fn display_info<'a>(&self, buf: &'a mut [u8; 128]) -> &'a str {
+        let mut writer = ArrayWriter::new(buf);
+
+        // The longest possible output is far shorter than the buffer.
+        let _ = write!(
+            writer,
+            "{}x{}@{}bpp, pitch={}",
+            self.framebuffer_width,
+            self.framebuffer_height,
+            self.framebuffer_bpp,
+            self.framebuffer_pitch
+        );
+
+        writer.into_str()
+    }
+
+    /// Encodes the provided 8-bit color channels into a pixel value, using the mask sizes and
+    /// shifts of this framebuffer. Channels with a mask smaller than 8 bits keep their most
+    /// significant bits.
+    pub fn encode_rgb(&self, r: u8, g: u8, b: u8) -> u32 {
+        encode_channel(r, self.red_mask_size, self.red_mask_shift)
+            | encode_channel(g, self.green_mask_size, self.green_mask_shift)
+            | encode_channel(b, self.blue_mask_size, self.blue_mask_shift)
+    }
+
+    /// Decodes the provided pixel value into its 8-bit color channels, using the mask sizes and
+    /// shifts of this framebuffer. This is the inverse of [StivaleFramebufferTag::encode_rgb].
+    pub fn decode_rgb(&self, color: u32) -> (u8, u8, u8) {
+        (
+            decode_channel(color, self.red_mask_size, self.red_mask_shift),
+            decode_channel(color, self.green_mask_size, self.green_mask_shift),
+            decode_channel(color, self.blue_mask_size, self.blue_mask_shift),
+        )
+    }
+
+    /// Returns the squared euclidean distance between the colors of the two provided pixel
+    /// values, after decoding them with [StivaleFramebufferTag::decode_rgb]. This is useful for
+    /// finding the closest entry of a palette.
+    pub fn color_distance_sq(&self, a: u32, b: u32) -> u32 {
+        let (ar, ag, ab) = self.decode_rgb(a);
+        let (br, bg, bb) = self.decode_rgb(b);
+        let square = |x: u8, y: u8| (x.abs_diff(y) as u32).pow(2);
+
+        square(ar, br)
+            .saturating_add(square(ag, bg))
+            .saturating_add(square(ab, bb))
+    }
+
+    /// Writes the provided pixel value at the provided coordinates. Only the lowest
+    /// `framebuffer_bpp / 8` bytes of the value are written.
+    ///
+    /// ## Safety
+    /// The framebuffer address must be mapped and writable, and the coordinates must lie
+    /// within the framebuffer. In debug builds this function panics if the coordinates are out of
+    /// bounds.
+    pub unsafe fn write_pixel(&self, x: u16, y: u16, color: u32) {
+        let pixel = self.pixel_ptr(x, y);
+        let bytes = color.to_le_bytes();
+
+        for (i, byte) in bytes.iter().take(self.bytes_per_pixel()).enumerate() {
+            pixel.add(i).write_volatile(*byte);
+        }
+    }
+
+    /// Reads the pixel value at the provided coordinates.
+    ///
+    /// ## Safety
+    /// The framebuffer address must be mapped and readable, and the coordinates must lie
+    /// within the framebuffer. In debug builds this function panics if the coordinates are out of
+    /// bounds.
+    pub unsafe fn read_pixel(&self, x: u16, y: u16) -> u32 {
+        let pixel = self.pixel_ptr(x, y);
+        let mut bytes = [0u8; 4];
+
+        for (i, byte) in bytes.iter_mut().take(self.bytes_per_pixel()).enumerate() {
+            *byte = pixel.add(i).read_volatile();
+        }
+
+        u32::from_le_bytes(bytes)
+    }
+
+    /// Encodes the provided color channels and writes the resulting pixel at the provided
+    /// coordinates. See [StivaleFramebufferTag::encode_rgb] and
+    /// [StivaleFramebufferTag::write_pixel] for more information.
+    ///
+    /// ## Safety
+    /// Same as [StivaleFramebufferTag::write_pixel].
+    pub unsafe fn set_pixel_rgb(&self, x: u16, y: u16, r: u8, g: u8, b: u8) {
+        self.write_pixel(x, y, self.encode_rgb(r, g, b));
+    }
+
+    /// Reads the pixel at the provided coordinates and decodes it into its color channels. See
+    /// [StivaleFramebufferTag::read_pixel] and [StivaleFramebufferTag::decode_rgb] for more
+    /// information.
+    ///
+    /// ## Safety
+    /// Same as [StivaleFramebufferTag::read_pixel].
+    pub unsafe fn get_pixel_rgb(&self, x: u16, y: u16) -> (u8, u8, u8) {
+        self.decode_rgb(self.read_pixel(x, y))
+    }
+
+    /// Returns a pointer to the first byte of the pixel at the provided coordinates.
+    fn pixel_ptr(&self, x: u16, y: u16) -> *mut u8 {
+        debug_assert!(
+            x < self.framebuffer_width && y < self.framebuffer_height,
+            "pixel coordinates out of bounds"
+        );
+
+        let offset = y as usize * self.pitch_usize() + x as usize * self.bytes_per_pixel();
+        let base = super::utils::try_into_usize(self.framebuffer_addr)
+            .expect("framebuffer address does not fit in a pointer");
+
+        (base + offset) as *mut u8
+    }
+
+    /// Returns the stride of the framebuffer in bytes, which is the number of bytes per row
+    /// including any padding. This is the same as the framebuffer pitch.
+    pub fn stride_bytes(&self) -> usize {
+        self.pitch_usize()
+    }
+
+    /// Returns the stride of the framebuffer in pixels, which is the number of full pixels per
+    /// row. This may be larger than the framebuffer width if the rows are padded. Returns zero if
+    /// the framebuffer has less than 8 bits per pixel.
+    pub fn stride_pixels(&self) -> usize {
+        match self.bytes_per_pixel() {
+            0 => 0,
+            bytes_per_pixel => self.pitch_usize() / bytes_per_pixel,
+        }
+    }
+
+    /// Returns the number of character cells of the provided font size that fit in the
+    /// framebuffer, as `(framebuffer_width / font_w, framebuffer_height / font_h)`, which is the
+    /// number of columns and rows. A zero font dimension yields zero instead of dividing by zero.
+    pub fn rows_cols_at_font_size(&self, font_w: u16, font_h: u16) -> (u16, u16) {
+        (
+            self.framebuffer_width.checked_div(font_w).unwrap_or(0),
+            self.framebuffer_height.checked_div(font_h).unwrap_or(0),
+        )
+    }
+
+    /// Returns the offset in bytes, from the start of the framebuffer, of the top-left pixel of
+    /// the character cell at the provided column and row.
+    pub fn char_pixel_offset(&self, col: u16, row: u16, font_w: u16, font_h: u16) -> usize {
+        row as usize * font_h as usize * self.pitch_usize()
+            + col as usize * font_w as usize * self.bytes_per_pixel()
+    }
+}
+
+/// Scales an 8-bit color channel down to `size` bits and shifts it into place.
+fn encode_channel(value: u8, size: u8, shift: u8) -> u32 {
+    if size == 0 {
+        return 0;
+    }
+
+    let value = value as u32 >> (8 - size.min(8));
+    value.checked_shl(shift as u32).unwrap_or(0)
+}
+
+/// Extracts a `size` bit color channel and scales it back up to 8 bits.
+fn decode_channel(color: u32, size: u8, shift: u8) -> u8 {
+    if size == 0 {
+        return 0;
+    }
+
+    let size = size.min(8);
+    let value = color.checked_shr(shift as u32).unwrap_or(0) & ((1 << size) - 1);
+
+    (value << (8 - size)) as u8
 }
 
 /// If the terminal tag was requested through the terminal tag header and its supported by the stivale
 /// bootloader, this tag is returned to the kernel. This tag provides an interface to the stivale terminal.
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivaleTerminalTag {
     pub header: StivaleTagHeader,
     pub flags: u32,
@@ -66,6 +361,17 @@ pub struct StivaleTerminalTag {
 }
 
 impl StivaleTerminalTag {
+    /// Creates a terminal tag which is not linked to any other tag.
+    pub const fn new(flags: u32, cols: u16, rows: u16, term_write_addr: u64) -> Self {
+        Self {
+            header: StivaleTagHeader::new(tag_ids::TERMINAL),
+            flags,
+            cols,
+            rows,
+            term_write_addr,
+        }
+    }
+
     /// Returns the terminal write function provided by the terminal stivale tag. This function
     /// returns the transmuted function for you to simplify the process of passing the string as a raw pointer
     /// and passing the string length.
@@ -98,12 +404,26 @@ impl StivaleTerminalTag {
 
 /// This tag is used to get the location of the ACPI RSDP structure in memory.
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivaleRsdpTag {
     pub header: StivaleTagHeader,
     /// Pointer to the ACPI RSDP structure.
     pub rsdp: u64,
 }
 
+impl StivaleRsdpTag {
+    /// Creates a RSDP tag which is not linked to any other tag.
+    pub const fn new(rsdp: u64) -> Self {
+        Self {
+            header: StivaleTagHeader::new(tag_ids::RSDP),
+            rsdp,
+        }
+    }
+}
+
 /// The type of a memory map entry. The entries are guaranteed to be sorted by base address,
 /// lowest to highest.
 ///
@@ -113,6 +433,7 @@ pub struct StivaleRsdpTag {
 /// any other entry.
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "extra-traits", derive(Eq, Hash))]
 pub enum StivaleMemoryMapEntryType {
     /// Usable memory.
     Usable = 1,
@@ -134,6 +455,7 @@ pub enum StivaleMemoryMapEntryType {
 
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "extra-traits", derive(PartialEq, Eq, Hash))]
 pub struct StivaleMemoryMapEntry {
     /// Physical address of base of the memory section.
     pub base: u64,
@@ -146,10 +468,54 @@ pub struct StivaleMemoryMapEntry {
 }
 
 impl StivaleMemoryMapEntry {
-    /// Returns the end address of this memory region.
+    /// Creates a new memory map entry, which is mainly useful for bootloaders.
+    pub const fn new(base: u64, length: u64, entry_type: StivaleMemoryMapEntryType) -> Self {
+        Self {
+            base,
+            length,
+            entry_type,
+            _padding: 0,
+        }
+    }
+
+    /// Returns the end address of this memory region, saturated to `u64::MAX` if the region
+    /// extends to the top of the address space.
     #[inline]
     pub fn end_address(&self) -> u64 {
-        self.base + self.length
+        self.range().end_address()
+    }
+
+    /// Returns the end address of this memory region, or `None` if it does not fit in a `u64`
+    /// because the region extends to the top of the address space.
+    #[inline]
+    pub fn checked_end_address(&self) -> Option<u64> {
+        self.range().checked_end_address()
+    }
+
+    /// Returns the range of physical memory covered by this memory region.
+    #[inline]
+    pub fn range(&self) -> MemoryRange {
+        MemoryRange::new(self.base, self.length)
+    }
+
+    /// Returns the smallest range made of whole `page_size` pages covering this memory region,
+    /// which is needed to map regions that are not page aligned, such as ACPI tables. Returns
+    /// `None` if the aligned range extends beyond the top of the address space. See
+    /// [MemoryRange::aligned].
+    ///
+    /// ## Panics
+    /// Panics if `page_size` is zero.
+    pub fn aligned_range(&self, page_size: u64) -> Option<MemoryRange> {
+        self.range().aligned(page_size)
+    }
+
+    /// Returns the number of `page_size` pages needed to map this memory region. See
+    /// [MemoryRange::page_count].
+    ///
+    /// ## Panics
+    /// Panics if `page_size` is zero.
+    pub fn page_count(&self, page_size: u64) -> Option<u64> {
+        self.range().page_count(page_size)
     }
 
     /// Returns the entry type of this memory region. External function is required
@@ -158,9 +524,17 @@ impl StivaleMemoryMapEntry {
     pub fn entry_type(&self) -> StivaleMemoryMapEntryType {
         self.entry_type
     }
+
+    /// Returns true if the provided address lies within this memory region. The base address
+    /// is inclusive and the end address is exclusive.
+    #[inline]
+    pub fn contains(&self, address: u64) -> bool {
+        self.range().contains(address)
+    }
 }
 
 #[repr(C)]
+#[cfg_attr(feature = "extra-traits", derive(Debug, PartialEq, Eq, Hash))]
 pub struct StivaleMemoryMapTag {
     pub header: StivaleTagHeader,
     /// Total length of the memory map entries.
@@ -186,16 +560,332 @@ impl StivaleMemoryMapTag {
         slice_ptr as *mut Self
     }
 
+    /// Writes the header of a memory map tag with `entry_count` entries at `ptr`, and returns a
+    /// pointer to the tag. The entries themselves are left untouched and have to be written
+    /// by the caller.
+    ///
+    /// # Safety
+    /// `ptr` must be 8-byte aligned and valid for writes of the whole tag, including the
+    /// entries.
+    pub unsafe fn init_header(ptr: *mut (), entry_count: u64) -> *mut Self {
+        let words = ptr as *mut u64;
+        words.write(tag_ids::MEMORY_MAP);
+        words.add(1).write(0);
+        words.add(2).write(entry_count);
+
+        Self::new_from_ptr_count(ptr, entry_count)
+    }
+
     /// Returns an iterator over all the memory regions.
-    pub fn iter(&self) -> StivaleMemoryMapIter {
+    pub fn iter(&self) -> StivaleMemoryMapIter<'_> {
         StivaleMemoryMapIter {
             sref: self,
             current: 0x00,
-            phantom: PhantomData::default(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the memory map entry containing the provided address, if any. As the entries are
+    /// sorted by base address, the search stops at the first entry starting after the address.
+    pub fn region_at_address(&self, address: u64) -> Option<&StivaleMemoryMapEntry> {
+        self.iter()
+            .take_while(|entry| entry.base <= address)
+            .find(|entry| entry.contains(address))
+    }
+
+    /// Binary searches the memory map for an entry whose base address is `address`. Following the
+    /// convention of [slice::binary_search_by_key], returns `Ok(index)` of the matching entry, or
+    /// `Err(index)` of where an entry with that base address would be inserted. This relies on the
+    /// entries being sorted by base address.
+    pub fn binary_search_by_address(&self, address: u64) -> Result<usize, usize> {
+        self.as_slice()
+            .binary_search_by_key(&address, |entry| entry.base)
+    }
+
+    /// Returns the memory map entry containing the provided address, if any. This is an
+    /// `O(log n)` version of [StivaleMemoryMapTag::region_at_address].
+    pub fn region_at_address_fast(&self, address: u64) -> Option<&StivaleMemoryMapEntry> {
+        let index = match self.binary_search_by_address(address) {
+            Ok(index) => index,
+            // The entry before the insertion point is the only one which can contain the address.
+            Err(index) => index.checked_sub(1)?,
+        };
+
+        let entry = &self.as_slice()[index];
+        entry.contains(address).then_some(entry)
+    }
+
+    /// Returns the first usable region which ends above the provided address, for example to
+    /// place data above the first MiB. The returned region may start below the address if it
+    /// contains it. This binary searches the entries, so it relies on them being sorted by base
+    /// address.
+    pub fn first_usable_region_at_or_above(&self, address: u64) -> Option<&StivaleMemoryMapEntry> {
+        let entries = self.as_slice();
+        let first = entries.partition_point(|entry| entry.end_address() <= address);
+
+        entries[first..]
+            .iter()
+            .find(|entry| entry.entry_type() == StivaleMemoryMapEntryType::Usable)
+    }
+
+    /// Returns an iterator over the regions used by the bootloader, which can be reclaimed once
+    /// the kernel no longer needs the boot information. See [ReclaimPlan] for the order in which
+    /// this has to be done.
+    pub fn reclaimable(&self) -> impl Iterator<Item = &StivaleMemoryMapEntry> + '_ {
+        self.iter()
+            .filter(|entry| entry.entry_type() == StivaleMemoryMapEntryType::BootloaderReclaimable)
+    }
+
+    /// Returns the sum of the lengths of the bootloader reclaimable regions.
+    pub fn total_reclaimable(&self) -> u64 {
+        self.reclaimable()
+            .fold(0u64, |total, entry| total.saturating_add(entry.length))
+    }
+
+    /// Returns the sum of the lengths of all the entries, regardless of their type. This is the
+    /// amount of physical memory described by the memory map, excluding the gaps between the
+    /// entries, and is not the amount of memory available to the kernel.
+    pub fn total_memory_bytes(&self) -> u64 {
+        self.iter()
+            .fold(0u64, |total, entry| total.saturating_add(entry.length))
+    }
+
+    /// Returns the distance from the lowest base address to the highest end address of the
+    /// entries, including the gaps between them. This is the size of the physical address space
+    /// to cover with a bitmap of physical memory. Returns `None` if the memory map is empty.
+    pub fn span_bytes(&self) -> Option<u64> {
+        let lowest = self.iter().map(|entry| entry.base).min()?;
+        let highest = self.iter().map(|entry| entry.end_address()).max()?;
+
+        Some(highest - lowest)
+    }
+
+    /// Writes the memory map as a table with one `base..end  size  type` row per entry, without
+    /// allocating. Sizes are printed in the largest of GiB, MiB or KiB which divides them.
+    pub fn fmt_table(&self, w: &mut dyn core::fmt::Write) -> core::fmt::Result {
+        crate::common::fmt_memory_map_table(
+            w,
+            self.iter()
+                .map(|entry| (entry.base, entry.length, entry.entry_type() as u32)),
+        )
+    }
+
+    /// Computes the main inputs of a page frame allocator in a single pass over the memory map.
+    /// See [PageFrameHint] for the meaning of each field.
+    ///
+    /// ## Panics
+    /// Panics if `page_size` is zero.
+    pub fn to_page_frame_allocator_hint(&self, page_size: u64) -> PageFrameHint {
+        assert!(page_size != 0, "page size must be non-zero");
+
+        let mut hint = PageFrameHint {
+            first_usable: 0,
+            usable_page_count: 0,
+            max_physical_addr: 0,
+        };
+        let mut first_usable = None;
+
+        for entry in self.iter() {
+            hint.max_physical_addr = hint.max_physical_addr.max(entry.end_address());
+
+            if entry.entry_type() == StivaleMemoryMapEntryType::Usable {
+                first_usable =
+                    Some(first_usable.map_or(entry.base, |base: u64| base.min(entry.base)));
+                hint.usable_page_count = hint
+                    .usable_page_count
+                    .saturating_add(entry.length / page_size);
+            }
+        }
+
+        hint.first_usable = first_usable.unwrap_or(0);
+        hint
+    }
+
+    /// Returns an iterator over the parts of the usable memory regions which do not overlap with
+    /// any of the holes. A region bisected by a hole is split in two. Unlike
+    /// [StivaleMemoryMapTag::subtract_regions], the holes have to be sorted by base address and
+    /// must not overlap each other, which lets each region be split after a binary search.
+    ///
+    /// Returns an error if the holes are not sorted or overlap.
+    pub fn usable_excluding<'b>(
+        &self,
+        holes: &'b [MemoryRange],
+    ) -> Result<UsableExcludingIter<'_, 'b>, UnsortedHolesError> {
+        if let Some(index) = holes
+            .windows(2)
+            .position(|pair| pair[1].base < pair[0].end_address())
+        {
+            return Err(UnsortedHolesError { index: index + 1 });
+        }
+
+        Ok(UsableExcludingIter {
+            entries: self.iter(),
+            holes,
+            pending: None,
+        })
+    }
+
+    /// Returns the first usable region which is at least `min_size` bytes long once its base
+    /// is aligned up to `align`. If `below` is provided, the returned range is clipped to end at
+    /// or before that address, which is needed for example by the real mode trampoline of the
+    /// APs, which has to lie below 1 MiB.
+    ///
+    /// ## Panics
+    /// Panics if `align` is zero.
+    pub fn find_usable(
+        &self,
+        min_size: u64,
+        align: u64,
+        below: Option<u64>,
+    ) -> Option<MemoryRange> {
+        self.aligned_usable_ranges(align, below)
+            .find(|range| range.length >= min_size)
+    }
+
+    /// Returns the largest usable region which is at least `min_size` bytes long once its base
+    /// is aligned up to `align`. If several regions have the same length, the first one is
+    /// returned.
+    ///
+    /// ## Panics
+    /// Panics if `align` is zero.
+    pub fn find_largest_usable(&self, min_size: u64, align: u64) -> Option<MemoryRange> {
+        self.aligned_usable_ranges(align, None)
+            .filter(|range| range.length >= min_size)
+            .fold(None, |largest: Option<MemoryRange>, range| match largest {
+                Some(largest) if largest.length >= range.length => Some(largest),
+                _ => Some(range),
+            })
+    }
+
+    /// Returns the usable regions with their base aligned up to `align` and their end clipped
+    /// to `below`, skipping the regions which become empty.
+    fn aligned_usable_ranges(
+        &self,
+        align: u64,
+        below: Option<u64>,
+    ) -> impl Iterator<Item = MemoryRange> + '_ {
+        assert!(align != 0, "alignment must be non-zero");
+
+        self.iter()
+            .filter(|entry| entry.entry_type() == StivaleMemoryMapEntryType::Usable)
+            .filter_map(move |entry| {
+                let base = entry.base.checked_next_multiple_of(align)?;
+                let end = entry.end_address();
+                let end = below.map_or(end, |below| end.min(below));
+
+                (base < end).then(|| MemoryRange::new(base, end - base))
+            })
+    }
+
+    /// Returns an iterator over the base addresses of all the `page_size` sized page frames
+    /// lying within the usable memory regions. The start of each region is rounded up and the
+    /// end rounded down to a multiple of `page_size`. The frames are yielded lazily, so this
+    /// does not allocate.
+    ///
+    /// ## Panics
+    /// Panics if `page_size` is zero.
+    pub fn iter_page_frames(&self, page_size: u64) -> StivalePageFrameIter<'_> {
+        assert!(page_size != 0, "page size must be non-zero");
+
+        StivalePageFrameIter {
+            frames: FrameWalker::new(self.iter().filter_map(usable_range), page_size),
+        }
+    }
+
+    /// Returns an iterator over the parts of the usable memory regions which do not overlap with
+    /// any of the excluded `(base, length)` ranges, for example the kernel image, its stack or
+    /// the page tables. A region bisected by an excluded range is split in two, and all yielded
+    /// entries are of type [StivaleMemoryMapEntryType::Usable].
+    pub fn subtract_regions<I>(&self, excluded: I) -> SubtractedMapIter<'_, I>
+    where
+        I: Iterator<Item = (u64, u64)> + Clone,
+    {
+        SubtractedMapIter {
+            entries: self.iter(),
+            excluded,
+            pending: None,
+        }
+    }
+}
+
+/// Iterator over the usable memory regions with the holes punched out. See
+/// [StivaleMemoryMapTag::usable_excluding] for more information.
+#[derive(Clone)]
+pub struct UsableExcludingIter<'a, 'b> {
+    /// The memory map entries that have not been looked at yet.
+    entries: StivaleMemoryMapIter<'a>,
+    /// The holes, sorted by base address and not overlapping each other.
+    holes: &'b [MemoryRange],
+    /// The `(base, end)` remainder of the region which is currently being split.
+    pending: Option<(u64, u64)>,
+}
+
+impl Iterator for UsableExcludingIter<'_, '_> {
+    type Item = MemoryRange;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (base, end) = match self.pending.take() {
+                Some(range) => range,
+                None => {
+                    let entry = self
+                        .entries
+                        .find(|entry| entry.entry_type() == StivaleMemoryMapEntryType::Usable)?;
+
+                    (entry.base, entry.end_address())
+                }
+            };
+
+            if base >= end {
+                continue;
+            }
+
+            // The holes do not overlap, so their end addresses are sorted as well, and the first
+            // hole which may overlap with the region is the first one ending after its base.
+            let first = self
+                .holes
+                .partition_point(|hole| hole.end_address() <= base);
+            let hole = self.holes[first..]
+                .iter()
+                .find(|hole| hole.length != 0)
+                .filter(|hole| hole.base < end);
+
+            match hole {
+                Some(hole) => {
+                    let hole_end = hole.end_address();
+
+                    if hole_end < end {
+                        self.pending = Some((hole_end, end));
+                    }
+
+                    if hole.base > base {
+                        return Some(MemoryRange::new(base, hole.base - base));
+                    }
+                }
+                None => return Some(MemoryRange::new(base, end - base)),
+            }
         }
     }
 }
 
+/// Error returned by [StivaleMemoryMapTag::usable_excluding] if the holes are not sorted by base
+/// address or overlap each other.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnsortedHolesError {
+    /// The index of the first hole starting before the end of the previous hole.
+    pub index: usize,
+}
+
+impl core::fmt::Display for UnsortedHolesError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "hole {} starts before the end of the previous hole",
+            self.index
+        )
+    }
+}
+
 /// Iterator over all the memory regions provided by the stivale bootloader.
 #[derive(Clone)]
 pub struct StivaleMemoryMapIter<'a> {
@@ -206,6 +896,17 @@ pub struct StivaleMemoryMapIter<'a> {
     phantom: PhantomData<&'a StivaleMemoryMapEntry>,
 }
 
+impl<'a> StivaleMemoryMapIter<'a> {
+    /// Returns the next entry without advancing the iterator.
+    pub fn peek(&self) -> Option<&'a StivaleMemoryMapEntry> {
+        if self.current < self.sref.entries_len {
+            Some(&self.sref.as_slice()[self.current as usize])
+        } else {
+            None
+        }
+    }
+}
+
 impl<'a> Iterator for StivaleMemoryMapIter<'a> {
     type Item = &'a StivaleMemoryMapEntry;
 
@@ -221,14 +922,143 @@ impl<'a> Iterator for StivaleMemoryMapIter<'a> {
     }
 }
 
+/// Iterator over the usable memory regions with the excluded ranges removed. See
+/// [StivaleMemoryMapTag::subtract_regions] for more information.
+#[derive(Clone)]
+pub struct SubtractedMapIter<'a, I> {
+    /// The memory map entries that have not been looked at yet.
+    entries: StivaleMemoryMapIter<'a>,
+    /// The `(base, length)` ranges to exclude from the usable regions.
+    excluded: I,
+    /// The `(base, end)` remainder of the region which is currently being split.
+    pending: Option<(u64, u64)>,
+}
+
+impl<'a, I> Iterator for SubtractedMapIter<'a, I>
+where
+    I: Iterator<Item = (u64, u64)> + Clone,
+{
+    type Item = StivaleMemoryMapEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (base, end) = match self.pending.take() {
+                Some(range) => range,
+                None => {
+                    let entry = self
+                        .entries
+                        .find(|entry| entry.entry_type == StivaleMemoryMapEntryType::Usable)?;
+
+                    (entry.base, entry.end_address())
+                }
+            };
+
+            // Of all the excluded ranges overlapping the region, the one starting first bounds
+            // the part of the region which is free of any overlap.
+            let overlap = self
+                .excluded
+                .clone()
+                .map(|(ex_base, ex_length)| (ex_base, ex_base.saturating_add(ex_length)))
+                .filter(|&(ex_base, ex_end)| ex_base < ex_end && ex_base < end && ex_end > base)
+                .min_by_key(|&(ex_base, _)| ex_base);
+
+            let free_end = match overlap {
+                Some((ex_base, ex_end)) => {
+                    if ex_end < end {
+                        self.pending = Some((ex_end, end));
+                    }
+
+                    ex_base.max(base)
+                }
+                None => end,
+            };
+
+            if free_end > base {
+                return Some(StivaleMemoryMapEntry {
+                    base,
+                    length: free_end - base,
+                    entry_type: StivaleMemoryMapEntryType::Usable,
+                    _padding: 0,
+                });
+            }
+        }
+    }
+}
+
+/// Summary of the memory map used to set up a page frame allocator, returned by
+/// [StivaleMemoryMapTag::to_page_frame_allocator_hint].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PageFrameHint {
+    /// The smallest base address of the usable entries, or zero if there is none.
+    pub first_usable: u64,
+    /// The sum of the number of whole pages in each usable entry. The entries are not aligned
+    /// to the page size, so this is an upper bound of the number of frames which can be
+    /// allocated.
+    pub usable_page_count: u64,
+    /// The highest end address of all the entries, regardless of their type.
+    pub max_physical_addr: u64,
+}
+
+/// Iterator over the base addresses of all the page frames lying within the usable memory
+/// regions. See [StivaleMemoryMapTag::iter_page_frames] for more information.
+#[derive(Clone)]
+pub struct StivalePageFrameIter<'a> {
+    frames: FrameWalker<UsableRanges<'a>>,
+}
+
+/// The ranges of the usable memory map entries.
+type UsableRanges<'a> =
+    FilterMap<StivaleMemoryMapIter<'a>, fn(&'a StivaleMemoryMapEntry) -> Option<MemoryRange>>;
+
+/// Returns the range of the provided entry if it is usable.
+fn usable_range(entry: &StivaleMemoryMapEntry) -> Option<MemoryRange> {
+    (entry.entry_type() == StivaleMemoryMapEntryType::Usable).then(|| entry.range())
+}
+
+impl<'a> Iterator for StivalePageFrameIter<'a> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.frames.next()
+    }
+}
+
+// The memory map iterator keeps returning `None` once exhausted.
+impl FusedIterator for StivalePageFrameIter<'_> {}
+
 /// This tag is used to get the current UNIX epoch, as per RTC.
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivaleEpochTag {
     pub header: StivaleTagHeader,
     /// UNIX epoch at boot, which is read from system RTC.
     pub epoch: u64,
 }
 
+impl StivaleEpochTag {
+    /// Creates an epoch tag which is not linked to any other tag.
+    pub const fn new(epoch: u64) -> Self {
+        Self {
+            header: StivaleTagHeader::new(tag_ids::EPOCH),
+            epoch,
+        }
+    }
+
+    /// Returns the UNIX epoch at boot as the duration elapsed since `1970-01-01 00:00:00 UTC`.
+    pub fn epoch_as_duration_since_unix(&self) -> core::time::Duration {
+        core::time::Duration::from_secs(self.epoch)
+    }
+}
+
+impl From<&StivaleEpochTag> for core::time::Duration {
+    fn from(tag: &StivaleEpochTag) -> Self {
+        tag.epoch_as_duration_since_unix()
+    }
+}
+
 bitflags::bitflags! {
     /// Bitfield representing the firmware and boot flags passed by the bootloader.
     pub struct StivaleFirmwareTagFlags: u64 {
@@ -241,49 +1071,120 @@ bitflags::bitflags! {
 
 /// This tag is used to get the info about the firmware.
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivaleFirmwareTag {
     pub header: StivaleTagHeader,
     /// Flags telling about the firmware and boot flags passed by the bootloader.
     pub flags: StivaleFirmwareTagFlags,
 }
 
+impl StivaleFirmwareTag {
+    /// Creates a firmware tag which is not linked to any other tag.
+    pub const fn new(flags: StivaleFirmwareTagFlags) -> Self {
+        Self {
+            header: StivaleTagHeader::new(tag_ids::FIRMWARE),
+            flags,
+        }
+    }
+}
+
 /// This tag is used to get a pointer to the EFI system table if available.
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivaleEfiSystemTableTag {
     pub header: StivaleTagHeader,
     /// Address of the EFI system table.
     pub system_table_addr: u64,
 }
 
+impl StivaleEfiSystemTableTag {
+    /// Creates an EFI system table tag which is not linked to any other tag.
+    pub const fn new(system_table_addr: u64) -> Self {
+        Self {
+            header: StivaleTagHeader::new(tag_ids::EFI_SYSTEM_TABLE),
+            system_table_addr,
+        }
+    }
+}
+
 /// This tag is used to get the kernel with a pointer to a copy the raw executable
 /// file of the kernel that the bootloader loaded.
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivaleKernelFileTag {
     pub header: StivaleTagHeader,
     /// Address of the raw kernel file.
     pub kernel_file_addr: u64,
 }
 
+impl StivaleKernelFileTag {
+    /// Creates a kernel file tag which is not linked to any other tag.
+    pub const fn new(kernel_file_addr: u64) -> Self {
+        Self {
+            header: StivaleTagHeader::new(tag_ids::KERNEL_FILE),
+            kernel_file_addr,
+        }
+    }
+}
+
 /// This tag is used to get the slide that the bootloader applied over the kernel's load
 /// address as a positive offset.
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivaleKernelSlideTag {
     pub header: StivaleTagHeader,
     /// The kernel slide. See structure-level documentation for more information.
     pub kernel_slide: u64,
 }
 
+impl StivaleKernelSlideTag {
+    /// Creates a kernel slide tag which is not linked to any other tag.
+    pub const fn new(kernel_slide: u64) -> Self {
+        Self {
+            header: StivaleTagHeader::new(tag_ids::KERNEL_SLIDE),
+            kernel_slide,
+        }
+    }
+}
+
 /// This tag is used to get the kernel the command line string that was passed to it by
 /// the bootloader.
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivaleCommandLineTag {
     pub header: StivaleTagHeader,
     /// Pointer to a null-terminated cmdline.
     pub command_line: u64,
 }
 
+impl StivaleCommandLineTag {
+    /// Creates a command line tag which is not linked to any other tag.
+    pub const fn new(command_line: u64) -> Self {
+        Self {
+            header: StivaleTagHeader::new(tag_ids::COMMAND_LINE),
+            command_line,
+        }
+    }
+}
+
 /// This tag is used to get the EDID information as acquired by the firmware.
 #[repr(C)]
+#[cfg_attr(feature = "extra-traits", derive(Debug, PartialEq, Eq, Hash))]
 pub struct StivaleEdidInfoTag {
     pub header: StivaleTagHeader,
     /// Length of the EDID information array.
@@ -298,6 +1199,36 @@ impl StivaleEdidInfoTag {
         unsafe { core::slice::from_raw_parts(self.info_array.as_ptr(), self.edid_len as usize) }
     }
 
+    /// Returns the 3-letter ISA PnP manufacturer ID (for example `SAM`) as ASCII, decoded from
+    /// bytes 8 and 9 of the EDID. Each letter is stored in 5 bits of the big-endian value, with
+    /// `1` standing for `A`. Returns `None` if the EDID is shorter than 12 bytes or if a letter
+    /// is out of range.
+    pub fn manufacturer_id(&self) -> Option<[u8; 3]> {
+        let edid = self.as_slice().get(..12)?;
+        let id = u16::from_be_bytes([edid[8], edid[9]]);
+
+        let mut letters = [0; 3];
+
+        for (index, letter) in letters.iter_mut().enumerate() {
+            let value = (id >> (10 - index * 5)) as u8 & 0x1f;
+
+            if !(1..=26).contains(&value) {
+                return None;
+            }
+
+            *letter = b'A' - 1 + value;
+        }
+
+        Some(letters)
+    }
+
+    /// Returns the product ID, stored in bytes 10 and 11 of the EDID as a little-endian value.
+    /// Returns `None` if the EDID is shorter than 12 bytes.
+    pub fn product_id(&self) -> Option<u16> {
+        let edid = self.as_slice().get(..12)?;
+        Some(u16::from_le_bytes([edid[10], edid[11]]))
+    }
+
     /// # Safety
     /// `ptr` must be a pointer to a properly initialized [`StivaleEdidInfoTag`] struct with
     /// `edid_count` entries in the `info_array`
@@ -308,6 +1239,22 @@ impl StivaleEdidInfoTag {
         // DST field has the same length
         slice_ptr as *mut Self
     }
+
+    /// Writes the header of an EDID tag with `edid_len` bytes of EDID information at `ptr`, and
+    /// returns a pointer to the tag. The EDID information itself is left untouched and has to be
+    /// written by the caller.
+    ///
+    /// # Safety
+    /// `ptr` must be 8-byte aligned and valid for writes of the whole tag, including the EDID
+    /// information.
+    pub unsafe fn init_header(ptr: *mut (), edid_len: u64) -> *mut Self {
+        let words = ptr as *mut u64;
+        words.write(tag_ids::EDID_INFO);
+        words.add(1).write(0);
+        words.add(2).write(edid_len);
+
+        Self::new_from_ptr_count(ptr, edid_len)
+    }
 }
 
 /// This tag exists if MTRR write-combining for the framebuffer was requested and successfully enabled. See
@@ -318,14 +1265,24 @@ impl StivaleEdidInfoTag {
 #[deprecated(
     note = "This tag is deprecated and considered legacy. Use is discouraged and it may not be supported on newer bootloaders."
 )]
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivaleMtrrTag {
     pub header: StivaleTagHeader,
 }
 
 /// Structure representing a module, containing the information of a module that
 /// the bootloader loaded alongside the kernel.
+///
+/// Modules are ordered by their start address, then by their end address and then by their
+/// string, so that a slice of modules can be sorted by load address.
 #[repr(C)]
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "extra-traits", derive(Clone, Copy, Debug, Hash))]
 pub struct StivaleModule {
     /// Address where this module has been loaded.
     pub start: u64,
@@ -343,14 +1300,80 @@ impl StivaleModule {
         self.end - self.start
     }
 
+    /// Returns true if the provided address lies within this module. The start address is
+    /// inclusive and the end address is exclusive.
+    #[inline]
+    pub fn contains_address(&self, address: u64) -> bool {
+        address >= self.start && address < self.end
+    }
+
     /// Returns the ASCII 0-terminated string passed to the module as specified in the config file
     /// as a rust string.
     #[inline]
     pub fn as_str(&self) -> &str {
         super::utils::string_from_slice(&self.string)
     }
+
+    /// Returns the module string, or `None` if it is not 0-terminated or not valid UTF-8. Unlike
+    /// [StivaleModule::as_str], a malformed string is never truncated.
+    #[inline]
+    pub fn try_as_str(&self) -> Option<&str> {
+        super::utils::try_string_from_slice(&self.string)
+    }
+
+    /// Returns true if this module starts with the ELF magic (`0x7f`, `E`, `L`, `F`).
+    ///
+    /// ## Safety
+    /// The memory between the start and end address of this module must be mapped and
+    /// readable.
+    pub unsafe fn module_is_elf(&self) -> bool {
+        self.start != 0
+            && super::utils::try_into_usize(self.end).is_some()
+            && self.end.saturating_sub(self.start) >= 4
+            && (0..4).all(|i| self.read_byte(i) == ELF_MAGIC[i])
+    }
+
+    /// Returns the ELF class (`EI_CLASS`) of this module, which is `1` for 32-bit and `2` for
+    /// 64-bit objects, or `None` if the module is not an ELF file.
+    ///
+    /// ## Safety
+    /// See [StivaleModule::module_is_elf] for more information.
+    pub unsafe fn elf_class(&self) -> Option<u8> {
+        if self.module_is_elf() && self.size() > 4 {
+            Some(self.read_byte(4))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the target machine (`e_machine`) of this module, read in the byte order given by
+    /// `EI_DATA`, or `None` if the module is not an ELF file or its byte order is invalid.
+    ///
+    /// ## Safety
+    /// See [StivaleModule::module_is_elf] for more information.
+    pub unsafe fn elf_machine(&self) -> Option<u16> {
+        if !self.module_is_elf() || self.size() < 20 {
+            return None;
+        }
+
+        let bytes = [self.read_byte(18), self.read_byte(19)];
+
+        match self.read_byte(5) {
+            1 => Some(u16::from_le_bytes(bytes)),
+            2 => Some(u16::from_be_bytes(bytes)),
+            _ => None,
+        }
+    }
+
+    /// Reads the byte at the provided offset from the start of this module.
+    unsafe fn read_byte(&self, offset: usize) -> u8 {
+        (self.start as *const u8).add(offset).read_volatile()
+    }
 }
 
+/// The magic bytes at the start of every ELF file.
+const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+
 /// Iterator over all the modules that were loaded.
 #[derive(Clone)]
 pub struct StivaleModuleIter<'a> {
@@ -361,6 +1384,17 @@ pub struct StivaleModuleIter<'a> {
     phantom: PhantomData<&'a StivaleModule>,
 }
 
+impl<'a> StivaleModuleIter<'a> {
+    /// Returns the next entry without advancing the iterator.
+    pub fn peek(&self) -> Option<&'a StivaleModule> {
+        if self.current < self.sref.module_len {
+            Some(&self.sref.as_slice()[self.current as usize])
+        } else {
+            None
+        }
+    }
+}
+
 impl<'a> Iterator for StivaleModuleIter<'a> {
     type Item = &'a StivaleModule;
 
@@ -378,6 +1412,7 @@ impl<'a> Iterator for StivaleModuleIter<'a> {
 
 /// This tag is used to get the modules that the bootloader loaded alongside the kernel, if any.
 #[repr(C)]
+#[cfg_attr(feature = "extra-traits", derive(Debug))]
 pub struct StivaleModuleTag {
     pub header: StivaleTagHeader,
     /// Length of the modules array.
@@ -388,14 +1423,30 @@ pub struct StivaleModuleTag {
 
 impl StivaleModuleTag {
     /// Returns an iterator over all the modules that were loaded.
-    pub fn iter(&self) -> StivaleModuleIter {
+    pub fn iter(&self) -> StivaleModuleIter<'_> {
         StivaleModuleIter {
             sref: self,
             current: 0,
-            phantom: PhantomData::default(),
+            phantom: PhantomData,
         }
     }
 
+    /// Returns the first module which contains the provided address, if any.
+    pub fn find_by_address(&self, address: u64) -> Option<&StivaleModule> {
+        self.iter().find(|module| module.contains_address(address))
+    }
+
+    /// Returns an iterator over the modules which start with the ELF magic, skipping the other
+    /// modules such as initrds or fonts.
+    ///
+    /// ## Safety
+    /// The memory of every module in this tag must be mapped and readable. See
+    /// [StivaleModule::module_is_elf] for more information.
+    pub unsafe fn iter_elf_modules(&self) -> impl Iterator<Item = &StivaleModule> + '_ {
+        self.iter()
+            .filter(|module| unsafe { module.module_is_elf() })
+    }
+
     /// Return's the modules array pointer as a rust slice.
     pub fn as_slice(&self) -> &[StivaleModule] {
         unsafe {
@@ -413,10 +1464,30 @@ impl StivaleModuleTag {
         // DST field has the same length
         slice_ptr as *mut Self
     }
+
+    /// Writes the header of a modules tag with `module_count` modules at `ptr`, and returns a
+    /// pointer to the tag. The modules themselves are left untouched and have to be written
+    /// by the caller.
+    ///
+    /// # Safety
+    /// `ptr` must be 8-byte aligned and valid for writes of the whole tag, including the
+    /// modules.
+    pub unsafe fn init_header(ptr: *mut (), module_count: u64) -> *mut Self {
+        let words = ptr as *mut u64;
+        words.write(tag_ids::MODULES);
+        words.add(1).write(0);
+        words.add(2).write(module_count);
+
+        Self::new_from_ptr_count(ptr, module_count)
+    }
 }
 
 /// This tag is used to get the location of the SMBIOS entry points in memory.
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivaleSmbiosTag {
     pub header: StivaleTagHeader,
     /// Stivale specification says that the flags in this tag are for future use
@@ -428,8 +1499,24 @@ pub struct StivaleSmbiosTag {
     pub smbios_entry_64: u64,
 }
 
+impl StivaleSmbiosTag {
+    /// Creates a SMBIOS tag which is not linked to any other tag.
+    pub const fn new(flags: u64, smbios_entry_32: u64, smbios_entry_64: u64) -> Self {
+        Self {
+            header: StivaleTagHeader::new(tag_ids::SMBIOS),
+            flags,
+            smbios_entry_32,
+            smbios_entry_64,
+        }
+    }
+}
+
 /// SMP imformation structure.
+///
+/// The field layout follows the x86 flavour of the protocol, where processors are identified by
+/// their ACPI processor UID and LAPIC ID.
 #[repr(C)]
+#[cfg_attr(feature = "extra-traits", derive(Clone, Copy, PartialEq, Eq, Hash))]
 pub struct StivaleSmpInfo {
     /// ACPI Processor UID as specified by MADT.
     pub acpi_processor_uid: u32,
@@ -461,11 +1548,83 @@ pub struct StivaleSmpInfo {
     pub extra: u64,
 }
 
+impl StivaleSmpInfo {
+    /// Starts the AP described by this structure, by writing `stack_top` to
+    /// [StivaleSmpInfo::target_stack] and then atomically writing the address of `entry` to
+    /// [StivaleSmpInfo::goto_address], with release ordering. The AP starts executing `entry` on
+    /// the provided stack with a pointer to this structure as its argument.
+    ///
+    /// ## Safety
+    /// This structure must have been returned by a bootloader and must describe an AP (not the
+    /// BSP) which was not already started. The stack must conform to the rules described in
+    /// [StivaleSmpTag::as_slice_mut].
+    pub unsafe fn start_ap(
+        &mut self,
+        stack_top: u64,
+        entry: extern "C" fn(*const StivaleSmpInfo) -> !,
+    ) {
+        core::ptr::write_volatile(&mut self.target_stack, stack_top);
+
+        // The AP polls `goto_address`, so the stack must be written before it. The release store
+        // guarantees that the AP observes the stack once it observes the address.
+        let goto_address = &*(&mut self.goto_address as *mut u64 as *const AtomicU64);
+        goto_address.store(entry as usize as u64, Ordering::Release);
+    }
+}
+
+impl core::fmt::Debug for StivaleSmpInfo {
+    /// Formats the processor IDs, along with the stack and goto addresses as hexadecimal.
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("StivaleSmpInfo")
+            .field("acpi_processor_uid", &self.acpi_processor_uid)
+            .field("lapic_id", &self.lapic_id)
+            .field("target_stack", &format_args!("{:#x}", self.target_stack))
+            .field("goto_address", &format_args!("{:#x}", self.goto_address))
+            .finish()
+    }
+}
+
+/// This tag describes the processors of the system and is used to start the APs (every CPU
+/// except the BSP).
+///
+/// ## Rendezvous
+/// The kernel usually has to wait until all the APs have reached a certain point, for example
+/// after initialising their local state. A shared counter set to [StivaleSmpTag::ap_count] is
+/// decremented by every AP on arrival, with release ordering so that the work done by the AP
+/// before that point is visible to the BSP. The BSP spins until the counter reaches zero, with
+/// acquire ordering to pair with the decrements:
+///
+/// ```rust,no_run
+/// use core::sync::atomic::{AtomicUsize, Ordering};
+/// use stivale_boot::v2::{StivaleSmpInfo, StivaleStruct};
+///
+/// static PENDING_APS: AtomicUsize = AtomicUsize::new(0);
+///
+/// extern "C" fn ap_main(_info: *const StivaleSmpInfo) -> ! {
+///     // Initialise the per-CPU state...
+///
+///     PENDING_APS.fetch_sub(1, Ordering::Release);
+///     loop {}
+/// }
+///
+/// fn start_aps(stivale_struct: &mut StivaleStruct, mut allocate_stack: impl FnMut() -> u64) {
+///     let smp = stivale_struct.smp_mut().expect("no SMP information");
+///
+///     // The counter must be set before any AP is started.
+///     PENDING_APS.store(smp.ap_count(), Ordering::Relaxed);
+///     unsafe { smp.start_all_aps(&mut allocate_stack, ap_main) };
+///
+///     while PENDING_APS.load(Ordering::Acquire) != 0 {
+///         core::hint::spin_loop();
+///     }
+/// }
+/// ```
 #[repr(C)]
+#[cfg_attr(feature = "extra-traits", derive(PartialEq, Eq, Hash))]
 pub struct StivaleSmpTag {
     header: StivaleTagHeader,
     pub flags: StivaleSmpHeaderTagFlags,
-    /// LAPIC ID of the BSP (bootstrap processor).
+    /// LAPIC ID of the BSP (bootstrap processor). This is only meaningful on x86.
     pub bsp_lapic_id: u32,
     /// Stivale specification says that this field is reserved for future use.
     pub unused: u32,
@@ -486,6 +1645,12 @@ impl StivaleSmpTag {
         self.cpu_count
     }
 
+    /// Returns the number of APs, which is the number of logical CPUs excluding the BSP. See
+    /// the [type-level documentation](StivaleSmpTag) for a rendezvous using this count.
+    pub fn ap_count(&self) -> usize {
+        self.cpu_count.saturating_sub(1) as usize
+    }
+
     /// Return's the SMP info array pointer as a rust slice.
     pub fn as_slice(&self) -> &[StivaleSmpInfo] {
         unsafe {
@@ -501,18 +1666,40 @@ impl StivaleSmpTag {
     /// rules in order to not trigger UB:
     ///
     /// - Writing to [`StivaleSmpInfo::goto_address`] will cause it to start executing at the
-    /// provided address as such a proper stack must have been set at
-    /// [`StivaleSmpInfo::target_stack`] already if a stack is needed.
+    ///   provided address as such a proper stack must have been set at
+    ///   [`StivaleSmpInfo::target_stack`] already if a stack is needed.
     /// - The stack pointer written to [`StivaleSmpInfo::target_stack`] must not alias already
-    /// mapped memory, this means that the memory area dedicated to the stack must be exclusively
-    /// used for the AP stack and stack overflows can trigger UB (consider using a guard page).
+    ///   mapped memory, this means that the memory area dedicated to the stack must be exclusively
+    ///   used for the AP stack and stack overflows can trigger UB (consider using a guard page).
     /// - The address pointed by [`StivaleSmpInfo::goto_address`] must be that of a
-    /// `extern "C" fn(&'static StivaleSmpInfo) -> !`, this also means that once written this
-    /// struct must not be mutated any further.
+    ///   `extern "C" fn(&'static StivaleSmpInfo) -> !`, this also means that once written this
+    ///   struct must not be mutated any further.
     pub unsafe fn as_slice_mut(&mut self) -> &mut [StivaleSmpInfo] {
         core::slice::from_raw_parts_mut(self.smp_info_array.as_mut_ptr(), self.cpu_count as usize)
     }
 
+    /// Starts all the APs (every CPU except the BSP), each on its own stack. For every AP,
+    /// `stack_allocator` is called to get the top of its stack, which is written before the
+    /// address of `entry`. See [StivaleSmpInfo::start_ap] for more information.
+    ///
+    /// ## Safety
+    /// This tag must have been returned by a bootloader and none of the APs may have been started
+    /// already. Every stack returned by `stack_allocator` must conform to the rules described in
+    /// [StivaleSmpTag::as_slice_mut].
+    pub unsafe fn start_all_aps(
+        &mut self,
+        mut stack_allocator: impl FnMut() -> u64,
+        entry: extern "C" fn(*const StivaleSmpInfo) -> !,
+    ) {
+        let bsp_lapic_id = self.bsp_lapic_id;
+
+        for info in self.as_slice_mut() {
+            if info.lapic_id != bsp_lapic_id {
+                info.start_ap(stack_allocator(), entry);
+            }
+        }
+    }
+
     /// # Safety
     /// `ptr` must be a pointer to a *properly* initialized [`StivaleSmpTag`] struct with `cpu_count`
     /// entries in the `smp_info_array`.
@@ -523,27 +1710,127 @@ impl StivaleSmpTag {
         // so the DST field has the same length.
         slice_ptr as *mut Self
     }
+
+    /// Writes the fixed size fields of a SMP tag describing `cpu_count` processors at `ptr`,
+    /// and returns a pointer to the tag. The SMP information structures are left untouched and
+    /// have to be written by the caller.
+    ///
+    /// # Safety
+    /// `ptr` must be 8-byte aligned and valid for writes of the whole tag, including the SMP
+    /// information structures.
+    pub unsafe fn init_header(
+        ptr: *mut (),
+        flags: StivaleSmpHeaderTagFlags,
+        bsp_lapic_id: u32,
+        cpu_count: u64,
+    ) -> *mut Self {
+        let words = ptr as *mut u64;
+        words.write(tag_ids::SMP);
+        words.add(1).write(0);
+        words.add(2).write(flags.bits());
+        (words.add(3) as *mut u32).write(bsp_lapic_id);
+        (words.add(3) as *mut u32).add(1).write(0);
+        words.add(4).write(cpu_count);
+
+        Self::new_from_ptr_count(ptr, cpu_count)
+    }
+}
+
+impl core::fmt::Debug for StivaleSmpTag {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("StivaleSmpTag")
+            .field("identifier", &self.header.identifier)
+            .field("flags", &self.flags)
+            .field("bsp_lapic_id", &self.bsp_lapic_id)
+            .field("cpu_count", &self.cpu_count)
+            .field("smp_info_array", &self.as_slice())
+            .finish()
+    }
 }
 
 /// This tag reports that the kernel has been booted via PXE, and reports the server ip that
 /// it was booted from.
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivalePxeInfoTag {
     pub header: StivaleTagHeader,
     /// Server IP in network byte order.
     pub server_ip: u32,
 }
 
+impl StivalePxeInfoTag {
+    /// Creates a PXE server tag which is not linked to any other tag.
+    pub const fn new(server_ip: u32) -> Self {
+        Self {
+            header: StivaleTagHeader::new(tag_ids::PXE_INFO),
+            server_ip,
+        }
+    }
+}
+
 /// This tag reports that there is a memory mapped UART port and its address.
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivaleUartTag {
     pub header: StivaleTagHeader,
     /// The address of the UART port.
     pub address: u64,
 }
 
+impl StivaleUartTag {
+    /// Creates an UART tag which is not linked to any other tag.
+    pub const fn new(address: u64) -> Self {
+        Self {
+            header: StivaleTagHeader::new(tag_ids::UART),
+            address,
+        }
+    }
+}
+
+/// Early console output, backed by the stivale terminal if available or the memory mapped
+/// UART port otherwise. See [crate::v2::StivaleStruct::terminal_or_uart] for more information.
+#[derive(Clone, Copy)]
+pub enum EarlyConsole<'a> {
+    /// The terminal set up by the bootloader.
+    Terminal(&'a StivaleTerminalTag),
+    /// The memory mapped UART port reported by the bootloader.
+    Uart(&'a StivaleUartTag),
+}
+
+impl core::fmt::Write for EarlyConsole<'_> {
+    /// Writes the string to the active console.
+    ///
+    /// ## Safety
+    /// Like [StivaleTerminalTag::term_write], writing to the console is **not** thread safe.
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        match self {
+            EarlyConsole::Terminal(terminal) => terminal.term_write()(s),
+            EarlyConsole::Uart(uart) => {
+                let port =
+                    super::utils::try_into_usize(uart.address).ok_or(core::fmt::Error)? as *mut u8;
+
+                for byte in s.bytes() {
+                    unsafe { port.write_volatile(byte) }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
 /// This tag describes a device tree blob for the platform.
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivaleDeviceTreeTag {
     pub header: StivaleTagHeader,
     /// The address of the device tree blob.
@@ -552,15 +1839,44 @@ pub struct StivaleDeviceTreeTag {
     pub size: u64,
 }
 
+impl StivaleDeviceTreeTag {
+    /// Creates a device tree tag which is not linked to any other tag.
+    pub const fn new(address: u64, size: u64) -> Self {
+        Self {
+            header: StivaleTagHeader::new(tag_ids::DEVICE_TREE),
+            address,
+            size,
+        }
+    }
+}
+
 /// This tag describes the high physical memory location.
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivaleVMapTag {
     pub header: StivaleTagHeader,
     /// VMAP_HIGH, where the physical memory is mapped in the higher half.
     pub address: u64,
 }
 
+impl StivaleVMapTag {
+    /// Creates a VMAP tag which is not linked to any other tag.
+    pub const fn new(address: u64) -> Self {
+        Self {
+            header: StivaleTagHeader::new(tag_ids::VMAP),
+            address,
+        }
+    }
+}
+
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivaleKernelFileV2Tag {
     pub header: StivaleTagHeader,
     /// Address of the raw kernel file.
@@ -569,6 +1885,17 @@ pub struct StivaleKernelFileV2Tag {
     pub kernel_size: u64,
 }
 
+impl StivaleKernelFileV2Tag {
+    /// Creates a kernel file tag which is not linked to any other tag.
+    pub const fn new(kernel_start: u64, kernel_size: u64) -> Self {
+        Self {
+            header: StivaleTagHeader::new(tag_ids::KERNEL_FILE_V2),
+            kernel_start,
+            kernel_size,
+        }
+    }
+}
+
 bitflags::bitflags! {
     pub struct StivalePmrPermissionFlags: u64 {
         const EXECUTABLE = 1 << 0;
@@ -578,6 +1905,10 @@ bitflags::bitflags! {
 }
 
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivalePmr {
     pub base: u64,
     pub size: u64,
@@ -589,9 +1920,75 @@ impl StivalePmr {
     pub fn permissions(&self) -> StivalePmrPermissionFlags {
         StivalePmrPermissionFlags::from_bits_truncate(self.permissions)
     }
+
+    /// Returns true if this range is mapped as executable.
+    pub fn is_executable(&self) -> bool {
+        self.permissions()
+            .contains(StivalePmrPermissionFlags::EXECUTABLE)
+    }
+
+    /// Returns true if this range is mapped as writable.
+    pub fn is_writable(&self) -> bool {
+        self.permissions()
+            .contains(StivalePmrPermissionFlags::WRITABLE)
+    }
+
+    /// Returns true if this range is mapped as readable.
+    pub fn is_readable(&self) -> bool {
+        self.permissions()
+            .contains(StivalePmrPermissionFlags::READABLE)
+    }
+}
+
+/// Iterator over all the protected memory ranges (PMRs) of the kernel.
+#[derive(Clone)]
+pub struct StivalePmrIter<'a> {
+    /// The PMRs array of the stivale PMRs tag.
+    pmrs: &'a [StivalePmr],
+    /// The index of the PMR that we are about to yield from the front.
+    current: usize,
+    /// The index following the PMR that we are about to yield from the back.
+    back: usize,
 }
 
+impl<'a> Iterator for StivalePmrIter<'a> {
+    type Item = &'a StivalePmr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current < self.back {
+            let pmr = &self.pmrs[self.current];
+            self.current += 1;
+
+            Some(pmr)
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.back - self.current;
+        (remaining, Some(remaining))
+    }
+}
+
+impl DoubleEndedIterator for StivalePmrIter<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.current < self.back {
+            self.back -= 1;
+
+            Some(&self.pmrs[self.back])
+        } else {
+            None
+        }
+    }
+}
+
+impl ExactSizeIterator for StivalePmrIter<'_> {}
+
+impl FusedIterator for StivalePmrIter<'_> {}
+
 #[repr(C)]
+#[cfg_attr(feature = "extra-traits", derive(Debug, PartialEq, Eq, Hash))]
 pub struct StivalePmrsTag {
     pub header: StivaleTagHeader,
     /// Count of PMRs in following array.
@@ -601,6 +1998,31 @@ pub struct StivalePmrsTag {
 }
 
 impl StivalePmrsTag {
+    /// Returns an iterator over all the PMRs of the kernel.
+    ///
+    /// ## Example
+    /// ```rust,no_run
+    /// use stivale_boot::v2::StivaleStruct;
+    ///
+    /// fn count_executable_ranges(stivale_struct: &StivaleStruct) -> usize {
+    ///     let pmrs = stivale_struct.pmrs().expect("no PMRs");
+    ///
+    ///     pmrs.iter().filter(|p| p.is_executable()).count()
+    /// }
+    /// ```
+    pub fn iter(&self) -> StivalePmrIter<'_> {
+        // The count field is authoritative, the length of the DST tail is derived from it.
+        debug_assert_eq!(self.pmrs.len() as u64, self.pmr_count);
+
+        let pmrs = self.as_slice();
+
+        StivalePmrIter {
+            pmrs,
+            current: 0,
+            back: pmrs.len(),
+        }
+    }
+
     /// Return's the PMRs array pointer as a rust slice.
     pub fn as_slice(&self) -> &[StivalePmr] {
         unsafe { core::slice::from_raw_parts(self.pmrs.as_ptr(), self.pmr_count as usize) }
@@ -616,15 +2038,46 @@ impl StivalePmrsTag {
         // DST field has the same length.
         slice_ptr as *mut Self
     }
+
+    /// Writes the header of a PMRs tag with `pmr_count` PMRs at `ptr`, and returns a
+    /// pointer to the tag. The PMRs themselves are left untouched and have to be written
+    /// by the caller.
+    ///
+    /// # Safety
+    /// `ptr` must be 8-byte aligned and valid for writes of the whole tag, including the
+    /// PMRs.
+    pub unsafe fn init_header(ptr: *mut (), pmr_count: u64) -> *mut Self {
+        let words = ptr as *mut u64;
+        words.write(tag_ids::PMRS);
+        words.add(1).write(0);
+        words.add(2).write(pmr_count);
+
+        Self::new_from_ptr_count(ptr, pmr_count)
+    }
 }
 
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivaleKernelBaseAddressTag {
     pub header: StivaleTagHeader,
     pub physical_base_address: u64,
     pub virtual_base_address: u64,
 }
 
+impl StivaleKernelBaseAddressTag {
+    /// Creates a kernel base address tag which is not linked to any other tag.
+    pub const fn new(physical_base_address: u64, virtual_base_address: u64) -> Self {
+        Self {
+            header: StivaleTagHeader::new(tag_ids::KERNEL_BASE_ADDRESS),
+            physical_base_address,
+            virtual_base_address,
+        }
+    }
+}
+
 bitflags::bitflags! {
     pub struct StivaleBootVolumeTagFlags: u64 {
         const VOLUME_GUID    = 1 << 0;
@@ -632,10 +2085,17 @@ bitflags::bitflags! {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[repr(C)]
 pub struct StivaleGuid(u32, u16, u16, [u8; 8]);
 
+impl StivaleGuid {
+    /// Creates a GUID from its fields, in the order of the textual representation.
+    pub const fn new(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> Self {
+        Self(data1, data2, data3, data4)
+    }
+}
+
 #[cfg(feature = "uuid")]
 impl From<StivaleGuid> for uuid::Uuid {
     fn from(guid: StivaleGuid) -> Self {
@@ -644,9 +2104,1139 @@ impl From<StivaleGuid> for uuid::Uuid {
 }
 
 #[repr(C)]
+#[cfg_attr(
+    feature = "extra-traits",
+    derive(Clone, Copy, Debug, PartialEq, Eq, Hash)
+)]
 pub struct StivaleBootVolumeTag {
     pub header: StivaleTagHeader,
     pub flags: StivaleBootVolumeTagFlags,
     pub guid: StivaleGuid,
     pub part_guid: StivaleGuid,
 }
+
+impl StivaleBootVolumeTag {
+    /// Creates a boot volume tag which is not linked to any other tag.
+    pub const fn new(
+        flags: StivaleBootVolumeTagFlags,
+        guid: StivaleGuid,
+        part_guid: StivaleGuid,
+    ) -> Self {
+        Self {
+            header: StivaleTagHeader::new(tag_ids::BOOT_VOLUME),
+            flags,
+            guid,
+            part_guid,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::test_utils::v2::{dst_tag, entry, memory_map, module, modules};
+    use crate::v2::tag_ids;
+    use core::{fmt::Write, ptr};
+    use std::{vec, vec::Vec};
+
+    fn framebuffer(width: u16, height: u16, pitch: u16, bpp: u16) -> StivaleFramebufferTag {
+        StivaleFramebufferTag::new(
+            0,
+            width,
+            height,
+            pitch,
+            bpp,
+            MaskInfo { size: 8, shift: 16 },
+            MaskInfo { size: 8, shift: 8 },
+            MaskInfo { size: 8, shift: 0 },
+        )
+    }
+
+    fn sample_memory_map() -> Vec<u64> {
+        dst_tag(
+            tag_ids::MEMORY_MAP,
+            &[
+                entry(0x0, 0x1000, StivaleMemoryMapEntryType::Reserved),
+                entry(0x1000, 0x9f000, StivaleMemoryMapEntryType::Usable),
+                entry(0x100000, 0x100000, StivaleMemoryMapEntryType::Kernel),
+                entry(0x300000, 0x400000, StivaleMemoryMapEntryType::Usable),
+            ],
+        )
+    }
+
+    fn sample_modules() -> Vec<u64> {
+        dst_tag(
+            tag_ids::MODULES,
+            &[
+                module(0x1000, 0x2000, b"initrd"),
+                module(0x4000, 0x6000, b"font.psf"),
+            ],
+        )
+    }
+
+    extern "C" fn ap_entry(_: *const StivaleSmpInfo) -> ! {
+        unreachable!()
+    }
+
+    #[test]
+    fn framebuffer_size() {
+        assert_eq!(framebuffer(1024, 768, 4096, 32).size(), 4096 * 768);
+    }
+
+    #[test]
+    fn framebuffer_stride() {
+        let tag = framebuffer(1920, 1080, 7680, 32);
+
+        assert_eq!(tag.stride_bytes(), 7680);
+        assert_eq!(tag.stride_pixels(), 1920);
+        assert_eq!(framebuffer(1920, 1080, 7680, 4).stride_pixels(), 0);
+    }
+
+    #[test]
+    fn framebuffer_text_cells() {
+        let tag = framebuffer(1024, 768, 4096, 32);
+
+        assert_eq!(tag.rows_cols_at_font_size(8, 16), (128, 48));
+        assert_eq!(tag.rows_cols_at_font_size(9, 14), (113, 54));
+        assert_eq!(tag.rows_cols_at_font_size(0, 16), (0, 48));
+        assert_eq!(tag.rows_cols_at_font_size(8, 0), (128, 0));
+
+        assert_eq!(tag.char_pixel_offset(0, 0, 8, 16), 0);
+        assert_eq!(
+            tag.char_pixel_offset(2, 3, 8, 16),
+            3 * 16 * 4096 + 2 * 8 * 4
+        );
+    }
+
+    #[test]
+    fn framebuffer_usize_accessors() {
+        let tag = framebuffer(800, 600, 2400, 24);
+
+        assert_eq!(tag.width_usize(), 800);
+        assert_eq!(tag.height_usize(), 600);
+        assert_eq!(tag.pitch_usize(), 2400);
+        assert_eq!(tag.bpp_usize(), 24);
+        assert_eq!(tag.bytes_per_pixel(), 3);
+    }
+
+    #[test]
+    fn framebuffer_encode_decode_rgb() {
+        let tag = framebuffer(1024, 768, 4096, 32);
+
+        assert_eq!(tag.encode_rgb(0x12, 0x34, 0x56), 0x123456);
+        assert_eq!(tag.decode_rgb(0x00abcdef), (0xab, 0xcd, 0xef));
+    }
+
+    #[test]
+    fn framebuffer_aspect_ratio() {
+        let ratio = |width, height| framebuffer(width, height, width * 4, 32).aspect_ratio();
+
+        assert_eq!(ratio(1920, 1080), Some((16, 9)));
+        assert_eq!(ratio(1024, 768), Some((4, 3)));
+        assert_eq!(ratio(800, 600), Some((4, 3)));
+        assert_eq!(ratio(0, 768), None);
+    }
+
+    #[test]
+    fn framebuffer_display_info() {
+        let tag = framebuffer(1024, 768, 4096, 32);
+        let mut buf = [0; 128];
+
+        assert_eq!(tag.display_info(&mut buf), "1024x768@32bpp, pitch=4096");
+    }
+
+    #[test]
+    fn framebuffer_color_masks() {
+        let tag = framebuffer(1024, 768, 4096, 32);
+
+        assert_eq!(tag.green_mask(), MaskInfo { size: 8, shift: 8 });
+
+        let pixel =
+            tag.red_mask().apply(0x12) | tag.green_mask().apply(0x34) | tag.blue_mask().apply(0x56);
+        assert_eq!(pixel, tag.encode_rgb(0x12, 0x34, 0x56));
+    }
+
+    #[test]
+    fn framebuffer_encode_rgb_565() {
+        let mut tag = framebuffer(1024, 768, 2048, 16);
+        tag.red_mask_size = 5;
+        tag.red_mask_shift = 11;
+        tag.green_mask_size = 6;
+        tag.green_mask_shift = 5;
+        tag.blue_mask_size = 5;
+        tag.blue_mask_shift = 0;
+
+        assert_eq!(tag.encode_rgb(0xff, 0xff, 0xff), 0xffff);
+        assert_eq!(tag.encode_rgb(0xff, 0, 0), 0xf800);
+        assert_eq!(tag.decode_rgb(0xf800), (0xf8, 0, 0));
+    }
+
+    #[test]
+    fn framebuffer_color_distance_sq() {
+        let tag = framebuffer(1024, 768, 4096, 32);
+
+        assert_eq!(tag.color_distance_sq(0x123456, 0x123456), 0);
+        assert_eq!(tag.color_distance_sq(0xff0000, 0x000000), 255 * 255);
+        assert_eq!(tag.color_distance_sq(0xff0000, 0x00ff00), 2 * 255 * 255);
+        assert_eq!(tag.color_distance_sq(0xffffff, 0x000000), 3 * 255 * 255);
+        assert_eq!(tag.color_distance_sq(0x000010, 0x000013), 9);
+    }
+
+    #[test]
+    fn framebuffer_pixel_round_trip() {
+        let mut pixels = vec![0u32; 4 * 4];
+        let mut tag = framebuffer(4, 4, 16, 32);
+        tag.framebuffer_addr = pixels.as_mut_ptr() as u64;
+
+        unsafe {
+            tag.set_pixel_rgb(2, 1, 0xaa, 0xbb, 0xcc);
+            assert_eq!(tag.get_pixel_rgb(2, 1), (0xaa, 0xbb, 0xcc));
+        }
+
+        assert_eq!(pixels[4 + 2], 0xaabbcc);
+        assert_eq!(pixels.iter().filter(|&&pixel| pixel != 0).count(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "extra-traits")]
+    fn extra_traits() {
+        use std::format;
+
+        let tag = framebuffer(1024, 768, 4096, 32);
+        let copy = tag;
+        assert_eq!(copy, framebuffer(1024, 768, 4096, 32));
+        assert_ne!(copy, framebuffer(800, 600, 3200, 32));
+        assert!(format!("{:?}", tag).contains("framebuffer_width: 1024"));
+
+        let entry = entry(0x1000, 0x2000, StivaleMemoryMapEntryType::Usable);
+        assert_eq!(entry, entry.clone());
+        assert_eq!(
+            format!("{:?}", entry),
+            "StivaleMemoryMapEntry { base: 4096, length: 8192, entry_type: Usable, _padding: 0 }"
+        );
+
+        let mut buf = sample_memory_map();
+        assert!(format!("{:?}", memory_map(&mut buf)).contains("base: 3145728"));
+    }
+
+    #[test]
+    fn memory_map_slice_and_iter() {
+        let mut buf = sample_memory_map();
+        let map = memory_map(&mut buf);
+
+        assert_eq!(map.as_slice().len(), 4);
+        assert_eq!(map.iter().count(), 4);
+        assert_eq!(map.iter().nth(3).unwrap().base, 0x300000);
+    }
+
+    #[test]
+    fn memory_map_total_and_span() {
+        let mut buf = sample_memory_map();
+        let map = memory_map(&mut buf);
+
+        // The map has gaps between 0xa0000 and 0x100000, and between 0x200000 and 0x300000.
+        assert_eq!(map.total_memory_bytes(), 0x5a0000);
+        assert_eq!(map.span_bytes(), Some(0x700000));
+        assert!(map.total_memory_bytes() < map.span_bytes().unwrap());
+
+        let mut buf = dst_tag::<StivaleMemoryMapEntry>(tag_ids::MEMORY_MAP, &[]);
+        assert_eq!(memory_map(&mut buf).total_memory_bytes(), 0);
+        assert_eq!(memory_map(&mut buf).span_bytes(), None);
+    }
+
+    #[test]
+    fn memory_map_fmt_table() {
+        let mut buf = sample_memory_map();
+        let mut table = std::string::String::new();
+        memory_map(&mut buf).fmt_table(&mut table).unwrap();
+
+        assert_eq!(
+            table.lines().collect::<Vec<_>>(),
+            [
+                "0x0000000000000000..0x0000000000001000       4 KiB  reserved",
+                "0x0000000000001000..0x00000000000a0000     636 KiB  usable",
+                "0x0000000000100000..0x0000000000200000       1 MiB  kernel",
+                "0x0000000000300000..0x0000000000700000       4 MiB  usable",
+            ]
+        );
+    }
+
+    #[test]
+    fn memory_map_page_frame_hint() {
+        let mut buf = dst_tag(
+            tag_ids::MEMORY_MAP,
+            &[
+                entry(0x100000, 0x8000, StivaleMemoryMapEntryType::Usable),
+                entry(0x200000, 0x1000000, StivaleMemoryMapEntryType::Reserved),
+            ],
+        );
+
+        assert_eq!(
+            memory_map(&mut buf).to_page_frame_allocator_hint(0x1000),
+            PageFrameHint {
+                first_usable: 0x100000,
+                usable_page_count: 8,
+                max_physical_addr: 0x1200000,
+            }
+        );
+
+        // Overlapping entries from a bogus memory map must not overflow the page count.
+        let mut buf = dst_tag(
+            tag_ids::MEMORY_MAP,
+            &[
+                entry(0, u64::MAX, StivaleMemoryMapEntryType::Usable),
+                entry(0, u64::MAX, StivaleMemoryMapEntryType::Usable),
+            ],
+        );
+
+        assert_eq!(
+            memory_map(&mut buf)
+                .to_page_frame_allocator_hint(1)
+                .usable_page_count,
+            u64::MAX
+        );
+    }
+
+    #[test]
+    fn memory_map_page_frames() {
+        let mut buf = sample_memory_map();
+        let map = memory_map(&mut buf);
+
+        // 0x9f + 0x400 frames in the two usable regions.
+        assert_eq!(map.iter_page_frames(0x1000).count(), 0x49f);
+        assert_eq!(map.iter_page_frames(0x1000).next(), Some(0x1000));
+        assert_eq!(map.iter_page_frames(0x1000).last(), Some(0x6ff000));
+
+        // With 2 MiB pages, only a single aligned frame fits in the usable regions.
+        let mut frames = map.iter_page_frames(0x200000);
+        assert_eq!(frames.next(), Some(0x400000));
+        assert_eq!(frames.next(), None);
+        assert_eq!(frames.next(), None);
+    }
+
+    #[test]
+    fn memory_map_iter_peek() {
+        let mut buf = sample_memory_map();
+        let map = memory_map(&mut buf);
+        let mut iter = map.iter();
+
+        iter.next();
+        assert_eq!(iter.peek().unwrap().base, 0x1000);
+        assert_eq!(iter.peek().unwrap().base, 0x1000);
+        assert_eq!(iter.next().unwrap().base, 0x1000);
+        assert_eq!(iter.nth(1).unwrap().base, 0x300000);
+        assert!(iter.peek().is_none());
+    }
+
+    #[test]
+    fn memory_map_first_usable_region_at_or_above() {
+        let mut buf = sample_memory_map();
+        let memory_map = memory_map(&mut buf);
+        let base = |address| {
+            memory_map
+                .first_usable_region_at_or_above(address)
+                .map(|entry| entry.base)
+        };
+
+        assert_eq!(base(0), Some(0x1000));
+        // Within a usable region, the region itself is returned.
+        assert_eq!(base(0x50000), Some(0x1000));
+        assert_eq!(base(0x9ffff), Some(0x1000));
+        // In the gap before the kernel, and inside the kernel region.
+        assert_eq!(base(0xa0000), Some(0x300000));
+        assert_eq!(base(0x100000), Some(0x300000));
+        assert_eq!(base(0x6fffff), Some(0x300000));
+        // Beyond all the regions.
+        assert_eq!(base(0x700000), None);
+        assert_eq!(base(u64::MAX), None);
+    }
+
+    #[test]
+    fn memory_map_reclaimable() {
+        let mut buf = sample_memory_map();
+        assert_eq!(memory_map(&mut buf).total_reclaimable(), 0);
+
+        let mut buf = dst_tag(
+            tag_ids::MEMORY_MAP,
+            &[
+                entry(0x1000, 0x9f000, StivaleMemoryMapEntryType::Usable),
+                entry(
+                    0x100000,
+                    0x2000,
+                    StivaleMemoryMapEntryType::BootloaderReclaimable,
+                ),
+                entry(0x102000, 0x1000, StivaleMemoryMapEntryType::AcpiReclaimable),
+                entry(
+                    0x200000,
+                    0x10000,
+                    StivaleMemoryMapEntryType::BootloaderReclaimable,
+                ),
+            ],
+        );
+        let memory_map = memory_map(&mut buf);
+        let bases = memory_map
+            .reclaimable()
+            .map(|entry| entry.base)
+            .collect::<Vec<_>>();
+
+        assert_eq!(bases, [0x100000, 0x200000]);
+        assert_eq!(memory_map.total_reclaimable(), 0x12000);
+    }
+
+    #[test]
+    fn memory_map_entry_bounds() {
+        let entry = entry(0x1000, 0x1000, StivaleMemoryMapEntryType::Usable);
+
+        assert_eq!(entry.end_address(), 0x2000);
+        assert!(entry.contains(0x1000));
+        assert!(entry.contains(0x1fff));
+        assert!(!entry.contains(0x2000));
+        assert!(!entry.contains(0xfff));
+    }
+
+    #[test]
+    fn memory_map_entry_aligned_range() {
+        let acpi = entry(
+            0x7fe0080,
+            0x1f80,
+            StivaleMemoryMapEntryType::AcpiReclaimable,
+        );
+
+        assert_eq!(acpi.range(), MemoryRange::new(0x7fe0080, 0x1f80));
+        assert_eq!(
+            acpi.aligned_range(0x1000),
+            Some(MemoryRange::new(0x7fe0000, 0x2000))
+        );
+        assert_eq!(acpi.page_count(0x1000), Some(2));
+
+        let top = entry(
+            u64::MAX - 4096 + 1,
+            4096,
+            StivaleMemoryMapEntryType::Reserved,
+        );
+        assert_eq!(
+            top.aligned_range(0x1000),
+            Some(MemoryRange::new(u64::MAX - 4096 + 1, 4096))
+        );
+        assert_eq!(top.page_count(0x1000), Some(1));
+        assert_eq!(top.page_count(0x2000), Some(1));
+    }
+
+    #[test]
+    fn memory_map_entry_at_the_top_of_memory() {
+        let top = entry(
+            u64::MAX - 4096 + 1,
+            4096,
+            StivaleMemoryMapEntryType::Reserved,
+        );
+
+        assert_eq!(top.checked_end_address(), None);
+        assert_eq!(top.end_address(), u64::MAX);
+        assert!(top.contains(u64::MAX));
+        assert!(top.contains(u64::MAX - 4095));
+        assert!(!top.contains(u64::MAX - 4096));
+
+        let below = entry(u64::MAX - 8191, 4096, StivaleMemoryMapEntryType::Usable);
+        assert_eq!(below.checked_end_address(), Some(u64::MAX - 4095));
+
+        let mut buf = dst_tag(
+            tag_ids::MEMORY_MAP,
+            &[
+                entry(0x1000, 0x9f000, StivaleMemoryMapEntryType::Usable),
+                below,
+                top,
+            ],
+        );
+        let memory_map = memory_map(&mut buf);
+
+        assert_eq!(memory_map.span_bytes(), Some(u64::MAX - 0x1000));
+        assert_eq!(
+            memory_map
+                .to_page_frame_allocator_hint(4096)
+                .max_physical_addr,
+            u64::MAX
+        );
+        assert_eq!(
+            memory_map
+                .region_at_address_fast(u64::MAX)
+                .map(|entry| entry.base),
+            Some(u64::MAX - 4095)
+        );
+        assert_eq!(
+            memory_map.iter_page_frames(4096).last(),
+            Some(u64::MAX - 8191)
+        );
+    }
+
+    #[test]
+    fn memory_map_region_at_address() {
+        let mut buf = sample_memory_map();
+        let map = memory_map(&mut buf);
+
+        assert_eq!(map.region_at_address(0x1000).unwrap().base, 0x1000);
+        assert_eq!(map.region_at_address(0x6fffff).unwrap().base, 0x300000);
+        assert!(map.region_at_address(0x200000).is_none());
+        assert!(map.region_at_address(0x700000).is_none());
+    }
+
+    #[test]
+    fn memory_map_binary_search_by_address() {
+        let mut buf = sample_memory_map();
+        let map = memory_map(&mut buf);
+
+        assert_eq!(map.binary_search_by_address(0x100000), Ok(2));
+        assert_eq!(map.binary_search_by_address(0x200000), Err(3));
+        assert_eq!(map.binary_search_by_address(u64::MAX), Err(4));
+    }
+
+    #[test]
+    fn memory_map_region_at_address_fast() {
+        let mut buf = sample_memory_map();
+        let map = memory_map(&mut buf);
+
+        for address in [
+            0x0, 0xfff, 0x1000, 0x5000, 0x100000, 0x250000, 0x6fffff, 0x700000,
+        ] {
+            assert_eq!(
+                map.region_at_address_fast(address).map(|entry| entry.base),
+                map.region_at_address(address).map(|entry| entry.base),
+            );
+        }
+    }
+
+    fn subtract(excluded: &[(u64, u64)]) -> Vec<(u64, u64)> {
+        let mut buf = sample_memory_map();
+
+        memory_map(&mut buf)
+            .subtract_regions(excluded.iter().copied())
+            .inspect(|entry| assert_eq!(entry.entry_type, StivaleMemoryMapEntryType::Usable))
+            .map(|entry| (entry.base, entry.end_address()))
+            .collect()
+    }
+
+    fn usable_excluding(memory_map: &StivaleMemoryMapTag, holes: &[(u64, u64)]) -> Vec<(u64, u64)> {
+        let holes = holes
+            .iter()
+            .map(|&(base, length)| MemoryRange::new(base, length))
+            .collect::<Vec<_>>();
+
+        memory_map
+            .usable_excluding(&holes)
+            .unwrap()
+            .map(|range| (range.base, range.length))
+            .collect()
+    }
+
+    #[test]
+    fn memory_map_usable_excluding_region_edges() {
+        let mut buf = sample_memory_map();
+        let memory_map = memory_map(&mut buf);
+
+        assert_eq!(
+            usable_excluding(memory_map, &[]),
+            [(0x1000, 0x9f000), (0x300000, 0x400000)]
+        );
+        // Holes at the start and at the end of the regions only shrink them.
+        assert_eq!(
+            usable_excluding(
+                memory_map,
+                &[(0x0, 0x2000), (0x9f000, 0x1000), (0x600000, 0x100000)]
+            ),
+            [(0x2000, 0x9d000), (0x300000, 0x300000)]
+        );
+        // Empty holes are ignored.
+        assert_eq!(
+            usable_excluding(memory_map, &[(0x1000, 0), (0x2000, 0)]),
+            [(0x1000, 0x9f000), (0x300000, 0x400000)]
+        );
+    }
+
+    #[test]
+    fn memory_map_usable_excluding_splits() {
+        let mut buf = sample_memory_map();
+        let memory_map = memory_map(&mut buf);
+
+        // Several holes within one region, and a hole spanning the gap between the regions.
+        assert_eq!(
+            usable_excluding(
+                memory_map,
+                &[(0x4000, 0x1000), (0x8000, 0x2000), (0x90000, 0x280000)]
+            ),
+            [
+                (0x1000, 0x3000),
+                (0x5000, 0x3000),
+                (0xa000, 0x86000),
+                (0x310000, 0x3f0000)
+            ]
+        );
+        // Holes outside of the usable regions change nothing.
+        assert_eq!(
+            usable_excluding(memory_map, &[(0x100000, 0x100000), (0x800000, 0x1000)]),
+            [(0x1000, 0x9f000), (0x300000, 0x400000)]
+        );
+        // A hole covering all the usable memory.
+        assert_eq!(usable_excluding(memory_map, &[(0x0, u64::MAX)]), []);
+    }
+
+    #[test]
+    fn memory_map_usable_excluding_unsorted() {
+        let mut buf = sample_memory_map();
+        let memory_map = memory_map(&mut buf);
+        let error = |holes: &[MemoryRange]| memory_map.usable_excluding(holes).err();
+
+        assert_eq!(
+            error(&[
+                MemoryRange::new(0x1000, 0x1000),
+                MemoryRange::new(0x3000, 0x1000),
+                MemoryRange::new(0x2000, 0x1000)
+            ]),
+            Some(UnsortedHolesError { index: 2 })
+        );
+        assert_eq!(
+            error(&[
+                MemoryRange::new(0x1000, 0x2000),
+                MemoryRange::new(0x2000, 0x1000)
+            ]),
+            Some(UnsortedHolesError { index: 1 })
+        );
+        assert_eq!(
+            error(&[
+                MemoryRange::new(0x1000, 0x1000),
+                MemoryRange::new(0x2000, 0x1000)
+            ]),
+            None
+        );
+    }
+
+    #[test]
+    fn memory_map_find_usable() {
+        let mut buf = dst_tag(
+            tag_ids::MEMORY_MAP,
+            &[
+                entry(0x0, 0x1000, StivaleMemoryMapEntryType::Reserved),
+                entry(0x1800, 0x7800, StivaleMemoryMapEntryType::Usable),
+                entry(0x80000, 0x90000, StivaleMemoryMapEntryType::Usable),
+                entry(0x200000, 0x300000, StivaleMemoryMapEntryType::Usable),
+                entry(0x500001, 0x380000, StivaleMemoryMapEntryType::Usable),
+            ],
+        );
+        let memory_map = memory_map(&mut buf);
+
+        // Aligning the base of the first usable region shrinks it from 0x7800 to 0x7000 bytes.
+        assert_eq!(
+            memory_map.find_usable(0x1000, 0x1000, None),
+            Some(MemoryRange::new(0x2000, 0x7000))
+        );
+        assert_eq!(
+            memory_map.find_usable(0x7800, 0x1000, None),
+            Some(MemoryRange::new(0x80000, 0x90000))
+        );
+        assert_eq!(
+            memory_map.find_usable(0x7000, 0x10000, None),
+            Some(MemoryRange::new(0x80000, 0x90000))
+        );
+
+        // The second usable region crosses 1 MiB, so it is clipped.
+        assert_eq!(
+            memory_map.find_usable(0x8000, 0x1000, Some(0x100000)),
+            Some(MemoryRange::new(0x80000, 0x80000))
+        );
+        assert_eq!(
+            memory_map.find_usable(0x90000, 0x1000, Some(0x100000)),
+            None
+        );
+        assert_eq!(memory_map.find_usable(0x1000, 0x1000, Some(0x1000)), None);
+
+        // The last region is the longest, but loses almost 2 MiB to the alignment.
+        assert_eq!(
+            memory_map.find_largest_usable(0, 0x1000),
+            Some(MemoryRange::new(0x501000, 0x37f001))
+        );
+        assert_eq!(
+            memory_map.find_largest_usable(0, 0x200000),
+            Some(MemoryRange::new(0x200000, 0x300000))
+        );
+        assert_eq!(memory_map.find_largest_usable(0x400000, 0x1000), None);
+    }
+
+    #[test]
+    fn memory_map_find_usable_at_the_top_of_memory() {
+        let mut buf = dst_tag(
+            tag_ids::MEMORY_MAP,
+            &[entry(
+                u64::MAX - 0x1800,
+                0x1801,
+                StivaleMemoryMapEntryType::Usable,
+            )],
+        );
+        let memory_map = memory_map(&mut buf);
+
+        // The end of the region is saturated, so the last byte of memory is not usable.
+        assert_eq!(
+            memory_map.find_usable(0xfff, 0x1000, None),
+            Some(MemoryRange::new(u64::MAX - 0xfff, 0xfff))
+        );
+        assert_eq!(memory_map.find_usable(0x1000, 0x1000, None), None);
+        // Aligning the base up would overflow.
+        assert_eq!(memory_map.find_largest_usable(0, 1 << 63), None);
+    }
+
+    #[test]
+    fn memory_map_subtract_nothing() {
+        assert_eq!(subtract(&[]), [(0x1000, 0xa0000), (0x300000, 0x700000)]);
+    }
+
+    #[test]
+    fn memory_map_subtract_partial_overlaps() {
+        assert_eq!(
+            subtract(&[(0x0, 0x2000), (0x6f0000, 0x20000)]),
+            [(0x2000, 0xa0000), (0x300000, 0x6f0000)]
+        );
+    }
+
+    #[test]
+    fn memory_map_subtract_bisecting() {
+        assert_eq!(
+            subtract(&[(0x500000, 0x1000), (0x400000, 0x1000), (0x5000, 0x0)]),
+            [
+                (0x1000, 0xa0000),
+                (0x300000, 0x400000),
+                (0x401000, 0x500000),
+                (0x501000, 0x700000),
+            ]
+        );
+    }
+
+    #[test]
+    fn memory_map_subtract_whole_regions() {
+        assert_eq!(
+            subtract(&[(0x0, 0x200000), (0x300000, 0x100000), (0x350000, 0x400000)]),
+            []
+        );
+    }
+
+    #[test]
+    fn module_string_and_size() {
+        let module = module(0x1000, 0x3000, b"initrd");
+
+        assert_eq!(module.as_str(), "initrd");
+        assert_eq!(module.size(), 0x2000);
+    }
+
+    #[test]
+    fn module_string_invalid_utf8() {
+        assert_eq!(module(0, 0, b"boot\xffimg").as_str(), "boot");
+        assert_eq!(module(0, 0, b"").as_str(), "");
+    }
+
+    #[test]
+    fn module_contains_address() {
+        let module = module(0x1000, 0x2000, b"");
+
+        assert!(module.contains_address(0x1000));
+        assert!(module.contains_address(0x1fff));
+        assert!(!module.contains_address(0x2000));
+        assert!(!module.contains_address(0xfff));
+    }
+
+    /// The first 20 bytes of the ELF header of a little endian x86_64 executable.
+    static ELF_HEADER: [u8; 20] = [
+        0x7f, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0x3e, 0,
+    ];
+
+    fn module_from_bytes(bytes: &[u8]) -> StivaleModule {
+        let start = bytes.as_ptr() as u64;
+        module(start, start + bytes.len() as u64, b"")
+    }
+
+    #[test]
+    fn module_ordering() {
+        let mut modules = [
+            module(0x4000, 0x6000, b"font.psf"),
+            module(0x1000, 0x3000, b"initrd"),
+            module(0x1000, 0x2000, b"kernel.sym"),
+            module(0x1000, 0x2000, b"initrd"),
+        ];
+        modules.sort_unstable();
+
+        let order = modules
+            .iter()
+            .map(|module| (module.start, module.end, module.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            [
+                (0x1000, 0x2000, "initrd"),
+                (0x1000, 0x2000, "kernel.sym"),
+                (0x1000, 0x3000, "initrd"),
+                (0x4000, 0x6000, "font.psf"),
+            ]
+        );
+
+        assert!(modules[0] == module(0x1000, 0x2000, b"initrd"));
+        assert!(modules[0] != modules[1]);
+    }
+
+    #[test]
+    fn module_elf_header() {
+        let module = module_from_bytes(&ELF_HEADER);
+
+        unsafe {
+            assert!(module.module_is_elf());
+            assert_eq!(module.elf_class(), Some(2));
+            assert_eq!(module.elf_machine(), Some(0x3e));
+        }
+    }
+
+    #[test]
+    fn module_elf_header_big_endian() {
+        let mut header = ELF_HEADER;
+        header[4] = 1;
+        header[5] = 2;
+        header[18] = 0;
+        header[19] = 0x14;
+        let module = module_from_bytes(&header);
+
+        unsafe {
+            assert_eq!(module.elf_class(), Some(1));
+            assert_eq!(module.elf_machine(), Some(0x14));
+        }
+    }
+
+    #[test]
+    fn module_not_elf() {
+        let bytes = *b"#!/bin/sh\necho hello";
+
+        unsafe {
+            assert!(!module_from_bytes(&bytes).module_is_elf());
+            assert!(!module_from_bytes(&ELF_HEADER[..3]).module_is_elf());
+            assert!(!module(0, 0x1000, b"").module_is_elf());
+            assert_eq!(module_from_bytes(&bytes).elf_class(), None);
+            assert_eq!(module_from_bytes(&ELF_HEADER[..16]).elf_machine(), None);
+        }
+    }
+
+    #[test]
+    fn module_tag_iter_and_peek() {
+        let mut buf = sample_modules();
+        let modules = modules(&mut buf);
+        let mut iter = modules.iter();
+
+        assert_eq!(modules.as_slice().len(), 2);
+        assert_eq!(iter.peek().unwrap().as_str(), "initrd");
+        assert_eq!(iter.next().unwrap().as_str(), "initrd");
+        assert_eq!(iter.next().unwrap().as_str(), "font.psf");
+        assert!(iter.peek().is_none());
+    }
+
+    #[test]
+    fn module_tag_iter_elf_modules() {
+        let script = *b"#!/bin/sh\necho hello";
+        let elf = |name| {
+            let start = ELF_HEADER.as_ptr() as u64;
+            module(start, start + ELF_HEADER.len() as u64, name)
+        };
+        let script = module(
+            script.as_ptr() as u64,
+            script.as_ptr() as u64 + script.len() as u64,
+            b"init.sh",
+        );
+        let mut buf = dst_tag(
+            tag_ids::MODULES,
+            &[elf(b"driver.ko"), script, elf(b"kernel.elf")],
+        );
+        let modules = modules(&mut buf);
+        let names = unsafe {
+            modules
+                .iter_elf_modules()
+                .map(StivaleModule::as_str)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(names, ["driver.ko", "kernel.elf"]);
+    }
+
+    #[test]
+    fn module_tag_find_by_address() {
+        let mut buf = sample_modules();
+        let modules = modules(&mut buf);
+        let find = |address| modules.find_by_address(address).map(StivaleModule::as_str);
+
+        assert_eq!(find(0x500), None);
+        assert_eq!(find(0x1800), Some("initrd"));
+        assert_eq!(find(0x3000), None);
+        assert_eq!(find(0x5fff), Some("font.psf"));
+        assert_eq!(find(0x6000), None);
+    }
+
+    #[test]
+    fn smp_tag_slice() {
+        let infos = [
+            StivaleSmpInfo {
+                acpi_processor_uid: 0,
+                lapic_id: 0,
+                target_stack: 0,
+                goto_address: 0,
+                extra: 0,
+            },
+            StivaleSmpInfo {
+                acpi_processor_uid: 1,
+                lapic_id: 2,
+                target_stack: 0,
+                goto_address: 0,
+                extra: 0,
+            },
+        ];
+        let mut buf = vec![0u64; 5 + 2 * 4];
+        buf[0] = tag_ids::SMP;
+        buf[4] = 2;
+
+        unsafe {
+            ptr::copy_nonoverlapping(infos.as_ptr(), buf.as_mut_ptr().add(5) as *mut _, 2);
+
+            let smp = &mut *StivaleSmpTag::new_from_ptr_count(buf.as_mut_ptr() as *mut (), 2);
+            assert_eq!(smp.cpu_count(), 2);
+            assert_eq!(smp.ap_count(), 1);
+            assert_eq!(smp.as_slice()[1].lapic_id, 2);
+
+            let mut stack = 0x10000;
+            smp.start_all_aps(
+                || {
+                    stack += 0x1000;
+                    stack
+                },
+                ap_entry,
+            );
+
+            assert_eq!(smp.as_slice()[0].goto_address, 0);
+            assert_eq!(smp.as_slice()[1].target_stack, 0x11000);
+            assert_eq!(smp.as_slice()[1].goto_address, ap_entry as *const () as u64);
+        }
+    }
+
+    #[test]
+    fn smp_debug() {
+        use std::format;
+
+        let info = StivaleSmpInfo {
+            acpi_processor_uid: 1,
+            lapic_id: 2,
+            target_stack: 0xdead0000,
+            goto_address: 0xffffffff80001000,
+            extra: 0,
+        };
+        let output = format!("{:?}", info);
+
+        assert!(output.contains("acpi_processor_uid: 1"));
+        assert!(output.contains("lapic_id: 2"));
+        assert!(output.contains("target_stack: 0xdead0000"));
+        assert!(output.contains("goto_address: 0xffffffff80001000"));
+
+        let mut buf = vec![0u64; 5 + 4];
+        buf[0] = tag_ids::SMP;
+        buf[3] = 2;
+        buf[4] = 1;
+
+        let smp = unsafe {
+            ptr::write(buf.as_mut_ptr().add(5) as *mut StivaleSmpInfo, info);
+            &*StivaleSmpTag::new_from_ptr_count(buf.as_mut_ptr() as *mut (), 1)
+        };
+        let output = format!("{:?}", smp);
+
+        assert!(output.contains(&format!("identifier: {}", tag_ids::SMP)));
+        assert!(output.contains("bsp_lapic_id: 2"));
+        assert!(output.contains("cpu_count: 1"));
+        assert!(output.contains("target_stack: 0xdead0000"));
+    }
+
+    #[test]
+    fn pmrs_permissions() {
+        let pmrs = [
+            StivalePmr {
+                base: 0xffffffff80000000,
+                size: 0x1000,
+                permissions: 0b101,
+            },
+            StivalePmr {
+                base: 0xffffffff80001000,
+                size: 0x1000,
+                permissions: 0b110 | 1 << 63,
+            },
+        ];
+        let mut buf = dst_tag(tag_ids::PMRS, &pmrs);
+        let tag = unsafe { &*StivalePmrsTag::new_from_ptr_count(buf.as_mut_ptr() as *mut (), 2) };
+
+        assert_eq!(tag.as_slice().len(), 2);
+        assert_eq!(
+            tag.as_slice()[0].permissions(),
+            StivalePmrPermissionFlags::EXECUTABLE | StivalePmrPermissionFlags::READABLE
+        );
+        assert_eq!(
+            tag.as_slice()[1].permissions(),
+            StivalePmrPermissionFlags::WRITABLE | StivalePmrPermissionFlags::READABLE
+        );
+    }
+
+    #[test]
+    fn pmrs_iter() {
+        let pmrs = [
+            StivalePmr {
+                base: 0xffffffff80000000,
+                size: 0x1000,
+                permissions: 0b101,
+            },
+            StivalePmr {
+                base: 0xffffffff80001000,
+                size: 0x1000,
+                permissions: 0b110,
+            },
+            StivalePmr {
+                base: 0xffffffff80002000,
+                size: 0x1000,
+                permissions: 0b100,
+            },
+        ];
+        let mut buf = dst_tag(tag_ids::PMRS, &pmrs);
+        let tag = unsafe { &*StivalePmrsTag::new_from_ptr_count(buf.as_mut_ptr() as *mut (), 3) };
+        let mut iter = tag.iter();
+
+        assert_eq!(iter.len(), 3);
+        assert_eq!(iter.next().unwrap().base, 0xffffffff80000000);
+        assert_eq!(iter.next_back().unwrap().base, 0xffffffff80002000);
+        assert_eq!(iter.len(), 1);
+        assert_eq!(iter.next_back().unwrap().base, 0xffffffff80001000);
+        assert!(iter.next().is_none());
+        assert!(iter.next_back().is_none());
+
+        assert_eq!(tag.iter().filter(|p| p.is_executable()).count(), 1);
+        assert_eq!(tag.iter().filter(|p| p.is_writable()).count(), 1);
+        assert_eq!(tag.iter().rev().filter(|p| p.is_readable()).count(), 3);
+    }
+
+    #[test]
+    fn edid_slice() {
+        let mut buf = dst_tag(tag_ids::EDID_INFO, &[0x00u8, 0xff, 0xff, 0x00]);
+        let tag =
+            unsafe { &*StivaleEdidInfoTag::new_from_ptr_count(buf.as_mut_ptr() as *mut (), 4) };
+
+        assert_eq!(tag.as_slice(), &[0x00, 0xff, 0xff, 0x00]);
+    }
+
+    #[test]
+    fn edid_identification() {
+        let blob: [u8; 12] = [
+            0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00, 0x4c, 0x2d, 0x8e, 0x0f,
+        ];
+        let mut buf = dst_tag(tag_ids::EDID_INFO, &blob);
+        let tag =
+            unsafe { &*StivaleEdidInfoTag::new_from_ptr_count(buf.as_mut_ptr() as *mut (), 12) };
+
+        assert_eq!(tag.manufacturer_id(), Some(*b"SAM"));
+        assert_eq!(tag.product_id(), Some(0x0f8e));
+
+        let mut buf = dst_tag(tag_ids::EDID_INFO, &blob[..11]);
+        let tag =
+            unsafe { &*StivaleEdidInfoTag::new_from_ptr_count(buf.as_mut_ptr() as *mut (), 11) };
+
+        assert_eq!(tag.manufacturer_id(), None);
+        assert_eq!(tag.product_id(), None);
+    }
+
+    #[test]
+    fn epoch_as_duration() {
+        let tag = StivaleEpochTag {
+            header: StivaleTagHeader {
+                identifier: tag_ids::EPOCH,
+                next: 0,
+            },
+            epoch: 1_600_000_000,
+        };
+
+        assert_eq!(tag.epoch_as_duration_since_unix().as_secs(), 1_600_000_000);
+        assert_eq!(core::time::Duration::from(&tag).as_secs(), 1_600_000_000);
+    }
+
+    #[test]
+    fn guid_hash_map_key() {
+        let data4 = [0x8e, 0x3a, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab];
+        let mut volumes = std::collections::HashMap::new();
+
+        volumes.insert(StivaleGuid::new(0x12345678, 0x9abc, 0xdef0, data4), "boot");
+        // An equal GUID hashes to the same bucket and replaces the first value.
+        volumes.insert(StivaleGuid::new(0x12345678, 0x9abc, 0xdef0, data4), "root");
+        volumes.insert(StivaleGuid::new(0x12345678, 0x9abc, 0xdef1, data4), "swap");
+
+        assert_eq!(volumes.len(), 2);
+        assert_eq!(
+            volumes[&StivaleGuid::new(0x12345678, 0x9abc, 0xdef0, data4)],
+            "root"
+        );
+    }
+
+    #[test]
+    fn early_console_uart() {
+        let mut port = 0u8;
+        let uart = StivaleUartTag {
+            header: StivaleTagHeader {
+                identifier: tag_ids::UART,
+                next: 0,
+            },
+            address: &mut port as *mut u8 as u64,
+        };
+
+        EarlyConsole::Uart(&uart).write_str("Hi!").unwrap();
+        assert_eq!(port, b'!');
+    }
+
+    #[test]
+    fn struct_tag_constructors() {
+        let tag = framebuffer(1920, 1080, 7680, 32);
+        assert_eq!(tag.header.identifier, tag_ids::FRAMEBUFFER);
+        assert_eq!(tag.memory_model, 1);
+        assert_eq!(tag.encode_rgb(0x12, 0x34, 0x56), 0x123456);
+
+        let tag = StivaleEpochTag::new(1_600_000_000);
+        assert_eq!(tag.header.identifier, tag_ids::EPOCH);
+        assert_eq!(tag.header.next, 0);
+        assert_eq!(tag.epoch, 1_600_000_000);
+
+        let tag = StivaleKernelBaseAddressTag::new(0x100000, 0xffffffff80000000);
+        assert_eq!(tag.header.identifier, tag_ids::KERNEL_BASE_ADDRESS);
+        assert_eq!(tag.virtual_base_address, 0xffffffff80000000);
+    }
+
+    #[test]
+    fn dst_tag_init_header() {
+        let mut buf = vec![0u64; 3 + 2 * 3];
+
+        unsafe {
+            let tag = &mut *StivaleMemoryMapTag::init_header(buf.as_mut_ptr() as *mut (), 2);
+            tag.entry_array[0] =
+                StivaleMemoryMapEntry::new(0, 0x1000, StivaleMemoryMapEntryType::Reserved);
+            tag.entry_array[1] =
+                StivaleMemoryMapEntry::new(0x1000, 0x9f000, StivaleMemoryMapEntryType::Usable);
+        }
+
+        assert_eq!(buf[0], tag_ids::MEMORY_MAP);
+        assert_eq!(memory_map(&mut buf).as_slice()[1].base, 0x1000);
+        assert_eq!(memory_map(&mut buf).total_memory_bytes(), 0xa0000);
+
+        let mut buf = vec![0u64; 5 + 4];
+
+        unsafe {
+            let smp = &*StivaleSmpTag::init_header(
+                buf.as_mut_ptr() as *mut (),
+                StivaleSmpHeaderTagFlags::empty(),
+                3,
+                1,
+            );
+            assert_eq!(smp.bsp_lapic_id, 3);
+            assert_eq!(smp.cpu_count(), 1);
+            assert_eq!(smp.as_slice().len(), 1);
+        }
+
+        assert_eq!(buf[0], tag_ids::SMP);
+    }
+}
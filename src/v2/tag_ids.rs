@@ -0,0 +1,79 @@
+//! Identifiers of the stivale2 structure tags passed by the bootloader to the kernel.
+
+/// Identifier of the command line structure tag.
+pub const COMMAND_LINE: u64 = 0xe5e76a1b4597a781;
+/// Identifier of the memory map structure tag.
+pub const MEMORY_MAP: u64 = 0x2187f79e8612de07;
+/// Identifier of the framebuffer structure tag.
+pub const FRAMEBUFFER: u64 = 0x506461d2950408fa;
+/// Identifier of the EDID information structure tag.
+pub const EDID_INFO: u64 = 0x968609d7af96b845;
+/// Identifier of the MTRR (legacy) structure tag.
+pub const MTRR: u64 = 0x6bc1a78ebe871172;
+/// Identifier of the terminal structure tag.
+pub const TERMINAL: u64 = 0xc2b3f4c3233b0974;
+/// Identifier of the modules structure tag.
+pub const MODULES: u64 = 0x4b6fe466aade04ce;
+/// Identifier of the RSDP structure tag.
+pub const RSDP: u64 = 0x9e1786930a375e78;
+/// Identifier of the SMBIOS structure tag.
+pub const SMBIOS: u64 = 0x274bd246c62bf7d1;
+/// Identifier of the epoch structure tag.
+pub const EPOCH: u64 = 0x566a7bed888e1407;
+/// Identifier of the firmware structure tag.
+pub const FIRMWARE: u64 = 0x359d837855e3858c;
+/// Identifier of the EFI system table structure tag.
+pub const EFI_SYSTEM_TABLE: u64 = 0x4bc5ec15845b558e;
+/// Identifier of the kernel file structure tag.
+pub const KERNEL_FILE: u64 = 0xe599d90c2975584a;
+/// Identifier of the kernel slide structure tag.
+pub const KERNEL_SLIDE: u64 = 0xee80847d01506c57;
+/// Identifier of the SMP structure tag.
+pub const SMP: u64 = 0x34d1d96339647025;
+/// Identifier of the PXE server info structure tag.
+pub const PXE_INFO: u64 = 0x29d1e96239247032;
+/// Identifier of the MMIO UART structure tag.
+pub const UART: u64 = 0xb813f9b8dbc78797;
+/// Identifier of the device tree blob structure tag.
+pub const DEVICE_TREE: u64 = 0xabb29bd49a2833fa;
+/// Identifier of the VMAP structure tag.
+pub const VMAP: u64 = 0xb0ed257db18cb58f;
+/// Identifier of the kernel file v2 structure tag.
+pub const KERNEL_FILE_V2: u64 = 0x37c13018a02c6ea2;
+/// Identifier of the PMRs structure tag.
+pub const PMRS: u64 = 0x5df266a64047b6bd;
+/// Identifier of the kernel base address structure tag.
+pub const KERNEL_BASE_ADDRESS: u64 = 0x060d78874a2a8af0;
+/// Identifier of the boot volume structure tag.
+pub const BOOT_VOLUME: u64 = 0x9b4358364c19ee62;
+
+/// Returns the name of the structure tag with the provided identifier, or `None` if the
+/// identifier is unknown.
+pub(crate) fn name(identifier: u64) -> Option<&'static str> {
+    match identifier {
+        COMMAND_LINE => Some("command_line"),
+        MEMORY_MAP => Some("memory_map"),
+        FRAMEBUFFER => Some("framebuffer"),
+        EDID_INFO => Some("edid_info"),
+        MTRR => Some("mtrr"),
+        TERMINAL => Some("terminal"),
+        MODULES => Some("modules"),
+        RSDP => Some("rsdp"),
+        SMBIOS => Some("smbios"),
+        EPOCH => Some("epoch"),
+        FIRMWARE => Some("firmware"),
+        EFI_SYSTEM_TABLE => Some("efi_system_table"),
+        KERNEL_FILE => Some("kernel_file"),
+        KERNEL_SLIDE => Some("kernel_slide"),
+        SMP => Some("smp"),
+        PXE_INFO => Some("pxe_info"),
+        UART => Some("uart"),
+        DEVICE_TREE => Some("dev_tree"),
+        VMAP => Some("vmap"),
+        KERNEL_FILE_V2 => Some("kernel_file_v2"),
+        PMRS => Some("pmrs"),
+        KERNEL_BASE_ADDRESS => Some("kernel_base_address"),
+        BOOT_VOLUME => Some("boot_volume"),
+        _ => None,
+    }
+}
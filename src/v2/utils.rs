@@ -1,10 +1,204 @@
-/// Helper function to validate and create a string from a slice.
-pub(crate) fn string_from_slice(slice: &[u8]) -> &str {
-    let mut length = 0;
+//! Helper functions for kernels using the stivale2 boot protocol.
 
-    while length < slice.len() && slice[length] != 0 {
-        length += 1;
+/// Converts an address passed by the bootloader to an `usize`. All the addresses in the
+/// stivale2 structures are 64-bit, even for 32-bit kernels, so this returns `None` if the
+/// address does not fit in a pointer.
+pub fn try_into_usize(address: u64) -> Option<usize> {
+    crate::common::try_into_usize(address)
+}
+
+pub(crate) use crate::common::{string_from_slice, try_string_from_slice};
+
+/// Returns the greatest common divisor of the two provided values, using the Euclidean
+/// algorithm. The greatest common divisor of zero and `n` is `n`.
+pub const fn gcd(mut a: u16, mut b: u16) -> u16 {
+    while b != 0 {
+        let remainder = a % b;
+        a = b;
+        b = remainder;
+    }
+
+    a
+}
+
+/// Returns a pointer to the top of the provided stack array, which can be passed to
+/// [crate::v2::StivaleHeader::stack]. Unlike `as_ptr_range()`, this function can be used in a
+/// `const` initialiser on any toolchain supporting const generics.
+///
+/// ## Example
+/// ```rust
+/// use stivale_boot::v2::{utils, StivaleHeader};
+///
+/// static STACK: [u8; 4096] = [0; 4096];
+///
+/// static STIVALE_HDR: StivaleHeader = StivaleHeader::new().stack(utils::stack_top(&STACK));
+/// ```
+pub const fn stack_top<const N: usize>(stack: &[u8; N]) -> *const u8 {
+    // SAFETY: A pointer one byte past the end of the array is within the bounds of the same
+    // allocated object.
+    unsafe { stack.as_ptr().add(N) }
+}
+
+/// Returns a pointer to the top of the provided stack, which is aligned to `ALIGN` bytes. As the
+/// address of the stack is not known at compile time, the alignment is guaranteed by its type:
+/// the stack has to be wrapped in a type aligned to at least `ALIGN` bytes, whose size is then a
+/// multiple of `ALIGN` as well.
+///
+/// ## Example
+/// ```rust
+/// use stivale_boot::v2::{utils, StivaleHeader};
+///
+/// #[repr(C, align(16))]
+/// struct Stack([u8; 4096]);
+///
+/// static STACK: Stack = Stack([0; 4096]);
+///
+/// static STIVALE_HDR: StivaleHeader =
+///     StivaleHeader::new().stack(utils::stack_top_aligned::<_, 16>(&STACK));
+/// ```
+///
+/// ## Panics
+/// Panics (at compile time, if used in a `const` initialiser) if `ALIGN` is not a power of two or
+/// is greater than the alignment of `T`.
+pub const fn stack_top_aligned<T, const ALIGN: usize>(stack: &T) -> *const u8 {
+    assert!(
+        ALIGN.is_power_of_two(),
+        "stack alignment must be a power of two"
+    );
+    assert!(
+        ALIGN <= core::mem::align_of::<T>(),
+        "the stack type is not aligned to the requested alignment"
+    );
+
+    // SAFETY: A pointer one byte past the end of the stack is within the bounds of the same
+    // allocated object.
+    unsafe { (stack as *const T as *const u8).add(core::mem::size_of::<T>()) }
+}
+
+/// Cursor over a byte buffer implementing [core::fmt::Write], for formatting strings without
+/// allocating.
+///
+/// ## Example
+/// ```rust
+/// use core::fmt::Write;
+/// use stivale_boot::v2::utils::ArrayWriter;
+///
+/// let mut buf = [0; 16];
+/// let mut writer = ArrayWriter::new(&mut buf);
+/// write!(writer, "{} CPUs", 4).unwrap();
+///
+/// assert_eq!(writer.as_str(), "4 CPUs");
+/// ```
+pub struct ArrayWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> ArrayWriter<'a> {
+    /// Creates an empty writer over the provided buffer.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    /// Returns the number of bytes written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if nothing has been written yet.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the string written so far.
+    pub fn as_str(&self) -> &str {
+        // SAFETY: Only whole `str`s, or prefixes of them ending on a character boundary, are
+        // copied into the buffer.
+        unsafe { core::str::from_utf8_unchecked(&self.buf[..self.len]) }
+    }
+
+    /// Consumes the writer, returning the string written with the lifetime of the buffer.
+    pub fn into_str(self) -> &'a str {
+        // SAFETY: See `as_str`.
+        unsafe { core::str::from_utf8_unchecked(&self.buf[..self.len]) }
     }
+}
+
+impl core::fmt::Write for ArrayWriter<'_> {
+    /// Appends the string to the buffer. If it does not fit, the longest prefix ending on a
+    /// character boundary is written and an error is returned.
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let available = self.buf.len() - self.len;
+        let mut length = s.len().min(available);
+
+        while !s.is_char_boundary(length) {
+            length -= 1;
+        }
+
+        self.buf[self.len..self.len + length].copy_from_slice(&s.as_bytes()[..length]);
+        self.len += length;
+
+        if length == s.len() {
+            Ok(())
+        } else {
+            Err(core::fmt::Error)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+
+    #[test]
+    fn gcd_euclid() {
+        assert_eq!(gcd(1920, 1080), 120);
+        assert_eq!(gcd(7, 13), 1);
+        assert_eq!(gcd(0, 768), 768);
+        assert_eq!(gcd(0, 0), 0);
+    }
+
+    #[test]
+    fn stack_top_offset() {
+        static STACK: [u8; 4096] = [0; 4096];
 
-    unsafe { core::str::from_utf8_unchecked(core::slice::from_raw_parts(slice.as_ptr(), length)) }
+        assert_eq!(stack_top(&STACK) as usize - STACK.as_ptr() as usize, 4096);
+    }
+
+    #[test]
+    fn stack_top_aligned_offset() {
+        // The length is not a multiple of the alignment, so the type is padded up to one.
+        #[repr(C, align(16))]
+        struct Stack([u8; 4100]);
+
+        static STACK: Stack = Stack([0; 4100]);
+        const TOP: *const u8 = stack_top_aligned::<_, 16>(&STACK);
+
+        let top = stack_top_aligned::<_, 16>(&STACK);
+        assert_eq!(top, TOP);
+        assert_eq!(top as usize % 16, 0);
+        assert_eq!(top as usize - STACK.0.as_ptr() as usize, 4112);
+        assert_eq!(stack_top_aligned::<_, 8>(&STACK), top);
+    }
+
+    #[test]
+    #[should_panic(expected = "not aligned to the requested alignment")]
+    fn stack_top_aligned_underaligned() {
+        static STACK: [u8; 4096] = [0; 4096];
+
+        stack_top_aligned::<_, 16>(&STACK);
+    }
+
+    #[test]
+    fn array_writer_overflow() {
+        let mut buf = [0; 8];
+        let mut writer = ArrayWriter::new(&mut buf);
+
+        assert!(writer.write_str("stivale").is_ok());
+        // The two bytes of the last character do not fit, so it is dropped entirely.
+        assert!(writer.write_str("\u{e9}").is_err());
+        assert_eq!(writer.len(), 7);
+        assert_eq!(writer.into_str(), "stivale");
+    }
 }
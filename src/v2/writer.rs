@@ -0,0 +1,403 @@
+//! Allocation-free writer for stivale2 structures, for bootloaders implementing the stivale2
+//! boot protocol. The [StructWriter] lays out the [StivaleStruct] and its tags in a buffer
+//! provided by the bootloader, linking every tag to the previous one.
+//!
+//! ## Example
+//! ```rust
+//! use stivale_boot::v2::writer::StructWriter;
+//! use stivale_boot::v2::{tag_ids, StivaleStruct};
+//!
+//! #[repr(C, align(8))]
+//! struct Buffer([u8; 512]);
+//!
+//! let mut buffer = Buffer([0; 512]);
+//! let mut writer = StructWriter::new(&mut buffer.0, "Limine", "2.0").unwrap();
+//!
+//! let command_line = writer.push_data(b"root=/dev/sda1\0").unwrap();
+//! writer
+//!     .push_raw_tag(tag_ids::COMMAND_LINE, &command_line.to_ne_bytes())
+//!     .unwrap();
+//!
+//! // The address of the structure is then passed to the kernel.
+//! let address = writer.struct_address();
+//! let stivale_struct = unsafe { &*(address as *const StivaleStruct) };
+//!
+//! assert_eq!(stivale_struct.bootloader_brand(), "Limine");
+//! assert_eq!(stivale_struct.command_line().unwrap().command_line, command_line);
+//! ```
+
+use core::{fmt, mem};
+
+use super::{
+    tag_ids, StivaleMemoryMapEntry, StivaleModule, StivaleSmpHeaderTagFlags, StivaleSmpInfo,
+    StivaleStruct, StivaleTagHeader,
+};
+
+/// The alignment of the structure and of every tag.
+const ALIGN: usize = mem::align_of::<u64>();
+
+/// The offset of the `tags` field in a [StivaleStruct].
+const TAGS_OFFSET: usize = 128;
+
+/// Error returned by a [StructWriter].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StructWriterError {
+    /// The buffer is too small for the structure or the tag being written.
+    BufferTooSmall,
+    /// The buffer or its base address is not 8-byte aligned.
+    Misaligned,
+    /// The bootloader brand or version does not fit in the structure, which holds up to 63
+    /// bytes followed by the 0-terminator.
+    StringTooLong,
+}
+
+impl fmt::Display for StructWriterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StructWriterError::BufferTooSmall => f.write_str("buffer too small"),
+            StructWriterError::Misaligned => f.write_str("buffer not 8-byte aligned"),
+            StructWriterError::StringTooLong => f.write_str("bootloader brand or version too long"),
+        }
+    }
+}
+
+/// Writer laying out a [StivaleStruct] and its tags in a caller-provided buffer. See the
+/// [module-level documentation](self) for an example.
+pub struct StructWriter<'buf> {
+    buf: &'buf mut [u8],
+    /// The address of the start of the buffer, as seen by the kernel.
+    base_address: u64,
+    /// The number of bytes written so far.
+    len: usize,
+    /// The offset of the `next` field of the last tag, or of the `tags` field of the structure
+    /// if no tag has been written yet.
+    next_field: usize,
+}
+
+impl<'buf> StructWriter<'buf> {
+    /// Creates a writer which writes the structure at the start of the provided buffer. The
+    /// addresses written in the structure are the addresses of the buffer itself, which is
+    /// correct if the kernel sees the buffer at the same address as the bootloader.
+    pub fn new(buf: &'buf mut [u8], brand: &str, version: &str) -> Result<Self, StructWriterError> {
+        let base_address = buf.as_ptr() as u64;
+        Self::with_base_address(buf, base_address, brand, version)
+    }
+
+    /// Creates a writer which writes the structure at the start of the provided buffer, with
+    /// the written addresses relative to `base_address`. This is used when the kernel sees the
+    /// buffer at a different address than the bootloader, for example its physical address.
+    pub fn with_base_address(
+        buf: &'buf mut [u8],
+        base_address: u64,
+        brand: &str,
+        version: &str,
+    ) -> Result<Self, StructWriterError> {
+        if !(buf.as_ptr() as usize).is_multiple_of(ALIGN)
+            || !base_address.is_multiple_of(ALIGN as u64)
+        {
+            return Err(StructWriterError::Misaligned);
+        }
+
+        if brand.len() >= 64 || version.len() >= 64 {
+            return Err(StructWriterError::StringTooLong);
+        }
+
+        let mut writer = Self {
+            buf,
+            base_address,
+            len: 0,
+            next_field: TAGS_OFFSET,
+        };
+
+        writer.reserve(mem::size_of::<StivaleStruct>())?;
+        writer.buf[..mem::size_of::<StivaleStruct>()].fill(0);
+        writer.buf[..brand.len()].copy_from_slice(brand.as_bytes());
+        writer.buf[64..64 + version.len()].copy_from_slice(version.as_bytes());
+        writer.len = mem::size_of::<StivaleStruct>();
+
+        Ok(writer)
+    }
+
+    /// Returns the address of the structure, which has to be passed to the kernel.
+    pub fn struct_address(&self) -> u64 {
+        self.base_address
+    }
+
+    /// Returns the number of bytes of the buffer used so far.
+    pub fn bytes_written(&self) -> usize {
+        self.len
+    }
+
+    /// Copies the provided data, for example a command line or a module name, into the buffer
+    /// and returns its address. The data is not part of the tag chain.
+    pub fn push_data(&mut self, data: &[u8]) -> Result<u64, StructWriterError> {
+        let offset = self.reserve(data.len())?;
+        self.write(data);
+
+        Ok(self.address_of(offset))
+    }
+
+    /// Appends a tag with the provided identifier, followed by the provided bytes, to the tag
+    /// chain and returns its address.
+    pub fn push_raw_tag(&mut self, identifier: u64, data: &[u8]) -> Result<u64, StructWriterError> {
+        self.push_tag_with(identifier, mem::size_of_val(data), |writer| {
+            writer.write(data)
+        })
+    }
+
+    /// Appends a copy of the provided tag to the tag chain and returns its address. The
+    /// identifier of the tag is kept and its `next` field is overwritten.
+    ///
+    /// ## Safety
+    /// `T` must be a `#[repr(C)]` tag structure starting with a [StivaleTagHeader] and it must
+    /// not contain any padding bytes.
+    pub unsafe fn push_tag<T>(&mut self, tag: &T) -> Result<u64, StructWriterError> {
+        let bytes = as_bytes(core::slice::from_ref(tag));
+        let identifier = (tag as *const T as *const StivaleTagHeader)
+            .read()
+            .identifier;
+
+        self.push_raw_tag(identifier, &bytes[mem::size_of::<StivaleTagHeader>()..])
+    }
+
+    /// Appends a memory map tag containing the provided entries to the tag chain and returns its
+    /// address.
+    pub fn push_memory_map(
+        &mut self,
+        entries: &[StivaleMemoryMapEntry],
+    ) -> Result<u64, StructWriterError> {
+        // SAFETY: memory map entries have explicit padding.
+        let bytes = unsafe { as_bytes(entries) };
+        self.push_dst_tag(tag_ids::MEMORY_MAP, &[], bytes, entries.len())
+    }
+
+    /// Appends a modules tag containing the provided modules to the tag chain and returns its
+    /// address. The payload of the modules has to be loaded separately.
+    pub fn push_modules(&mut self, modules: &[StivaleModule]) -> Result<u64, StructWriterError> {
+        // SAFETY: modules do not contain any padding bytes.
+        let bytes = unsafe { as_bytes(modules) };
+        self.push_dst_tag(tag_ids::MODULES, &[], bytes, modules.len())
+    }
+
+    /// Appends a SMP tag describing the provided processors to the tag chain and returns its
+    /// address.
+    pub fn push_smp(
+        &mut self,
+        flags: StivaleSmpHeaderTagFlags,
+        bsp_lapic_id: u32,
+        cpus: &[StivaleSmpInfo],
+    ) -> Result<u64, StructWriterError> {
+        let mut prefix = [0; 16];
+        prefix[..8].copy_from_slice(&flags.bits().to_ne_bytes());
+        prefix[8..12].copy_from_slice(&bsp_lapic_id.to_ne_bytes());
+
+        // SAFETY: SMP information structures do not contain any padding bytes.
+        let bytes = unsafe { as_bytes(cpus) };
+        self.push_dst_tag(tag_ids::SMP, &prefix, bytes, cpus.len())
+    }
+
+    /// Appends a tag made of the provided fixed size fields, an entry count and the provided
+    /// entries.
+    fn push_dst_tag(
+        &mut self,
+        identifier: u64,
+        prefix: &[u8],
+        entries: &[u8],
+        count: usize,
+    ) -> Result<u64, StructWriterError> {
+        let size = prefix.len() + mem::size_of::<u64>() + entries.len();
+
+        self.push_tag_with(identifier, size, |writer| {
+            writer.write(prefix);
+            writer.write(&(count as u64).to_ne_bytes());
+            writer.write(entries);
+        })
+    }
+
+    /// Appends a tag whose data after the tag header is `size` bytes long and written by `f`.
+    fn push_tag_with(
+        &mut self,
+        identifier: u64,
+        size: usize,
+        f: impl FnOnce(&mut Self),
+    ) -> Result<u64, StructWriterError> {
+        let offset = self.reserve(mem::size_of::<StivaleTagHeader>() + size)?;
+        let address = self.address_of(offset);
+
+        self.write(&identifier.to_ne_bytes());
+        self.write(&0u64.to_ne_bytes());
+        f(self);
+
+        let next_field = self.next_field;
+        self.buf[next_field..next_field + 8].copy_from_slice(&address.to_ne_bytes());
+        self.next_field = offset + mem::size_of::<u64>();
+
+        Ok(address)
+    }
+
+    /// Pads the buffer to the next aligned offset and makes sure that `size` bytes fit after it.
+    /// Returns the aligned offset, where the next write starts.
+    fn reserve(&mut self, size: usize) -> Result<usize, StructWriterError> {
+        let offset = self.len.next_multiple_of(ALIGN);
+
+        if offset
+            .checked_add(size)
+            .is_none_or(|end| end > self.buf.len())
+        {
+            return Err(StructWriterError::BufferTooSmall);
+        }
+
+        self.buf[self.len..offset].fill(0);
+        self.len = offset;
+
+        Ok(offset)
+    }
+
+    /// Writes the provided bytes after the last write. The space must have been reserved.
+    fn write(&mut self, bytes: &[u8]) {
+        self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    fn address_of(&self, offset: usize) -> u64 {
+        self.base_address + offset as u64
+    }
+}
+
+/// Returns the bytes making up the provided slice.
+///
+/// ## Safety
+/// `T` must not contain any padding bytes.
+unsafe fn as_bytes<T>(slice: &[T]) -> &[u8] {
+    core::slice::from_raw_parts(slice.as_ptr() as *const u8, mem::size_of_val(slice))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::v2::module;
+    use crate::v2::{StivaleEpochTag, StivaleMemoryMapEntryType};
+
+    #[repr(C, align(8))]
+    struct Buffer([u8; 1024]);
+
+    #[test]
+    fn round_trip() {
+        let mut buffer = Buffer([0xaa; 1024]);
+        let mut writer = StructWriter::new(&mut buffer.0, "Limine", "2.0").unwrap();
+
+        let memory_map = writer
+            .push_memory_map(&[
+                StivaleMemoryMapEntry::new(0x1000, 0x9f000, StivaleMemoryMapEntryType::Usable),
+                StivaleMemoryMapEntry::new(0x100000, 0x2000, StivaleMemoryMapEntryType::Kernel),
+            ])
+            .unwrap();
+        let epoch = StivaleEpochTag {
+            header: StivaleTagHeader {
+                identifier: tag_ids::EPOCH,
+                next: 0,
+            },
+            epoch: 1_600_000_000,
+        };
+        unsafe { writer.push_tag(&epoch) }.unwrap();
+        writer
+            .push_modules(&[module(0x200000, 0x201000, b"initrd")])
+            .unwrap();
+        writer
+            .push_smp(
+                StivaleSmpHeaderTagFlags::X2APIC,
+                1,
+                &[
+                    StivaleSmpInfo {
+                        acpi_processor_uid: 0,
+                        lapic_id: 1,
+                        target_stack: 0,
+                        goto_address: 0,
+                        extra: 0,
+                    },
+                    StivaleSmpInfo {
+                        acpi_processor_uid: 1,
+                        lapic_id: 4,
+                        target_stack: 0,
+                        goto_address: 0,
+                        extra: 0,
+                    },
+                ],
+            )
+            .unwrap();
+
+        assert!(writer.bytes_written().is_multiple_of(ALIGN));
+
+        let stivale_struct = unsafe { &*(writer.struct_address() as *const StivaleStruct) };
+        assert_eq!(stivale_struct.bootloader_brand(), "Limine");
+        assert_eq!(stivale_struct.bootloader_version(), "2.0");
+        assert_eq!(
+            stivale_struct.get_tag(tag_ids::MEMORY_MAP),
+            Some(memory_map)
+        );
+
+        let memory_map = stivale_struct.memory_map().unwrap();
+        assert_eq!(memory_map.as_slice().len(), 2);
+        assert_eq!(memory_map.as_slice()[1].base, 0x100000);
+        assert_eq!(stivale_struct.epoch().unwrap().epoch, 1_600_000_000);
+
+        let modules = stivale_struct.modules().unwrap();
+        assert_eq!(modules.as_slice().len(), 1);
+        assert_eq!(modules.as_slice()[0].as_str(), "initrd");
+
+        let smp = stivale_struct.smp().unwrap();
+        assert_eq!(smp.flags, StivaleSmpHeaderTagFlags::X2APIC);
+        assert_eq!(smp.bsp_lapic_id, 1);
+        assert_eq!(smp.as_slice().len(), 2);
+        assert_eq!(smp.as_slice()[1].lapic_id, 4);
+    }
+
+    #[test]
+    fn base_address() {
+        let mut buffer = Buffer([0; 1024]);
+        let mut writer =
+            StructWriter::with_base_address(&mut buffer.0, 0x8000, "Limine", "2.0").unwrap();
+
+        assert_eq!(writer.struct_address(), 0x8000);
+        assert_eq!(writer.push_data(b"data").unwrap(), 0x8000 + 136);
+        assert_eq!(
+            writer.push_raw_tag(tag_ids::EPOCH, &[0; 8]).unwrap(),
+            0x8000 + 144
+        );
+        assert_eq!(&buffer.0[128..136], &(0x8000u64 + 144).to_ne_bytes());
+    }
+
+    #[test]
+    fn errors() {
+        let mut buffer = Buffer([0; 1024]);
+
+        assert_eq!(
+            StructWriter::new(&mut buffer.0[1..], "", "").err(),
+            Some(StructWriterError::Misaligned)
+        );
+        assert_eq!(
+            StructWriter::with_base_address(&mut buffer.0, 0x8004, "", "").err(),
+            Some(StructWriterError::Misaligned)
+        );
+        assert_eq!(
+            StructWriter::new(&mut buffer.0[..128], "", "").err(),
+            Some(StructWriterError::BufferTooSmall)
+        );
+        assert_eq!(
+            StructWriter::new(
+                &mut buffer.0,
+                core::str::from_utf8(&[b'a'; 64]).unwrap(),
+                ""
+            )
+            .err(),
+            Some(StructWriterError::StringTooLong)
+        );
+
+        let mut writer = StructWriter::new(&mut buffer.0[..160], "", "").unwrap();
+        assert!(writer.push_raw_tag(tag_ids::EPOCH, &[0; 8]).is_ok());
+        assert_eq!(
+            writer.push_raw_tag(tag_ids::EPOCH, &[0; 8]),
+            Err(StructWriterError::BufferTooSmall)
+        );
+    }
+}
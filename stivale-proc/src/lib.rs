@@ -16,15 +16,22 @@ use proc_macro::TokenStream;
 /// #[stivale2hdr]
 /// static STIVALE_HDR: StivaleHeader = StivaleHeader::new();
 /// ```
+///
+/// When targeting `x86`, the header is also checked at compile time with
+/// `stivale_assert_valid_32bit!`, since 32-bit kernels need a stack or an entry point.
 #[proc_macro_attribute]
 pub fn stivale2hdr(_: TokenStream, item: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(item as syn::ItemStatic);
     let ty = &input.ty;
+    let ident = &input.ident;
 
     quote::quote! {
         // ensures that the type of the header is `v2::StivaleHeader`.
         const _: () = { fn __sheader_ty_chk(e: #ty) -> ::stivale_boot::v2::StivaleHeader { e } };
 
+        #[cfg(target_arch = "x86")]
+        ::stivale_boot::stivale_assert_valid_32bit!(#ident);
+
         #[link_section = ".stivale2hdr"]
         #[no_mangle]
         #[used]
@@ -0,0 +1,43 @@
+//! Utilities shared by the integration tests to fabricate boot structures in host memory.
+
+#![allow(dead_code)]
+
+use core::{mem, slice};
+
+/// A zeroed byte buffer aligned to 8 bytes, as required by the stivale structures and tags.
+#[repr(C, align(8))]
+pub struct Aligned<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> Aligned<N> {
+    pub fn new() -> Self {
+        Self([0; N])
+    }
+
+    pub fn address(&self) -> usize {
+        self.0.as_ptr() as usize
+    }
+
+    /// Reinterprets the start of the buffer as a `T`.
+    ///
+    /// ## Safety
+    /// Every bit pattern of the first `size_of::<T>()` bytes must be a valid `T`.
+    pub unsafe fn as_mut<T>(&mut self) -> &mut T {
+        assert!(mem::size_of::<T>() <= N && mem::align_of::<T>() <= 8);
+        &mut *(self.0.as_mut_ptr() as *mut T)
+    }
+}
+
+/// Returns the bytes of the provided value.
+///
+/// ## Safety
+/// `T` must not contain any padding bytes.
+pub unsafe fn as_bytes<T>(value: &T) -> &[u8] {
+    slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>())
+}
+
+/// Returns a module string padded to the 128 bytes of a module descriptor.
+pub fn module_string(name: &str) -> [u8; 128] {
+    let mut string = [0; 128];
+    string[..name.len()].copy_from_slice(name.as_bytes());
+    string
+}
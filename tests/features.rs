@@ -0,0 +1,29 @@
+//! Checks that the protocol modules follow the `v1` and `v2` features. Build the tests with
+//! `--no-default-features` and either feature to cover the whole matrix.
+
+#[test]
+fn protocol_modules() {
+    #[cfg(feature = "v1")]
+    let _ = stivale_boot::v1::StivaleHeader::new();
+
+    #[cfg(feature = "v2")]
+    let _ = stivale_boot::v2::StivaleHeader::new();
+
+    #[cfg(all(feature = "v1", feature = "v2"))]
+    let _ = stivale_boot::ProtocolHint::V1;
+
+    let _ = stivale_boot::common::MaskInfo::default();
+}
+
+#[test]
+#[cfg(feature = "helper-macros")]
+fn header_macro() {
+    use stivale_boot::v2::StivaleHeader;
+
+    static STACK: [u8; 4096] = [0; 4096];
+
+    #[stivale_boot::stivale2hdr]
+    static STIVALE_HDR: StivaleHeader = StivaleHeader::new().stack(STACK.as_ptr());
+
+    assert_eq!(STIVALE_HDR.get_stack(), STACK.as_ptr());
+}
@@ -0,0 +1,70 @@
+//! Exercises the stivale structure and header accessors against structures fabricated in host
+//! memory, without any bootloader involved.
+
+#![cfg(feature = "v1")]
+
+mod common;
+
+use common::{module_string, Aligned};
+use stivale_boot::v1::*;
+
+#[test]
+fn structure_fields() {
+    let mut buffer = Aligned::<256>::new();
+    let stivale_struct = unsafe { buffer.as_mut::<StivaleStruct>() };
+
+    assert_eq!(stivale_struct.rsdp_ptr(), None);
+    assert_eq!(stivale_struct.unix_epoch(), None);
+
+    stivale_struct.rsdp_adddres = 0xe0000;
+    stivale_struct.unix_epoch = 1_600_000_000;
+    stivale_struct.flags = 0b11 | 1 << 40;
+    stivale_struct.framebuffer_width = 1024;
+    stivale_struct.framebuffer_bpp = 24;
+    stivale_struct.red_mask_size = 8;
+    stivale_struct.red_mask_shift = 16;
+    stivale_struct.green_mask_size = 8;
+    stivale_struct.green_mask_shift = 8;
+    stivale_struct.blue_mask_size = 8;
+
+    assert_eq!(stivale_struct.rsdp_ptr(), Some(0xe0000 as *const u8));
+    assert_eq!(stivale_struct.unix_epoch(), Some(1_600_000_000));
+    assert_eq!(
+        stivale_struct.flags_v1(),
+        StivaleStructV1Flags::BIOS | StivaleStructV1Flags::EXTENDED_COLOUR_INFO
+    );
+    assert_eq!(stivale_struct.framebuffer_width_usize(), 1024);
+    assert_eq!(stivale_struct.framebuffer_bytes_per_pixel(), 3);
+
+    let pixel = stivale_struct.red_mask().apply(0x01)
+        | stivale_struct.green_mask().apply(0x02)
+        | stivale_struct.blue_mask().apply(0x03);
+    assert_eq!(pixel, 0x010203);
+}
+
+#[test]
+fn module() {
+    let module = StivaleModule {
+        start: 0x200000,
+        end: 0x204000,
+        string: module_string("kernel.sym"),
+    };
+
+    assert_eq!(module.as_str(), "kernel.sym");
+    assert_eq!(module.size(), 0x4000);
+    assert!(module.contains_address(0x200000));
+    assert!(!module.contains_address(0x204000));
+}
+
+#[test]
+fn header() {
+    static STACK: [u8; 4096] = [0; 4096];
+
+    let header = StivaleHeader::new()
+        .stack(STACK.as_ptr())
+        .flags(StivaleHeaderFlags::FRAMEBUFFER_MODE)
+        .framebuffer_bpp(32);
+
+    assert_eq!(core::mem::size_of_val(&header), 24);
+    assert!(header.validate_for_32bit());
+}
@@ -0,0 +1,232 @@
+//! Exercises the stivale2 structure and header accessors against structures fabricated in host
+//! memory, without any bootloader involved.
+
+#![cfg(feature = "v2")]
+
+mod common;
+
+use common::{as_bytes, module_string, Aligned};
+use stivale_boot::v2::writer::StructWriter;
+use stivale_boot::v2::*;
+
+const CPUS: [StivaleSmpInfo; 2] = [
+    StivaleSmpInfo {
+        acpi_processor_uid: 0,
+        lapic_id: 0,
+        target_stack: 0,
+        goto_address: 0,
+        extra: 0,
+    },
+    StivaleSmpInfo {
+        acpi_processor_uid: 1,
+        lapic_id: 1,
+        target_stack: 0,
+        goto_address: 0,
+        extra: 0,
+    },
+];
+
+/// Lays out a structure with a handful of fixed size and DST tags in the provided buffer and
+/// returns it.
+fn fabricate(buffer: &mut Aligned<4096>) -> &StivaleStruct {
+    let mut writer = StructWriter::new(&mut buffer.0, "Limine", "2.0").unwrap();
+
+    let command_line = writer.push_data(b"console=ttyS0\0").unwrap();
+    writer
+        .push_raw_tag(tag_ids::COMMAND_LINE, &command_line.to_ne_bytes())
+        .unwrap();
+
+    writer
+        .push_memory_map(&[
+            StivaleMemoryMapEntry::new(0x0, 0x1000, StivaleMemoryMapEntryType::Reserved),
+            StivaleMemoryMapEntry::new(0x1000, 0x9f000, StivaleMemoryMapEntryType::Usable),
+            StivaleMemoryMapEntry::new(0x100000, 0x100000, StivaleMemoryMapEntryType::Kernel),
+            StivaleMemoryMapEntry::new(0x200000, 0x600000, StivaleMemoryMapEntryType::Usable),
+        ])
+        .unwrap();
+
+    writer
+        .push_modules(&[
+            StivaleModule {
+                start: 0x400000,
+                end: 0x401000,
+                string: module_string("initrd"),
+            },
+            StivaleModule {
+                start: 0x500000,
+                end: 0x500800,
+                string: module_string("font.psf"),
+            },
+        ])
+        .unwrap();
+
+    let mut framebuffer = [0; 24];
+    framebuffer[..8].copy_from_slice(&0xfd000000u64.to_ne_bytes());
+    for (index, field) in [800u16, 600, 3200, 32].iter().enumerate() {
+        framebuffer[8 + index * 2..10 + index * 2].copy_from_slice(&field.to_ne_bytes());
+    }
+    framebuffer[16..23].copy_from_slice(&[1, 8, 16, 8, 8, 8, 0]);
+    writer
+        .push_raw_tag(tag_ids::FRAMEBUFFER, &framebuffer)
+        .unwrap();
+
+    writer
+        .push_smp(StivaleSmpHeaderTagFlags::X2APIC, 0, &CPUS)
+        .unwrap();
+
+    writer
+        .push_raw_tag(tag_ids::EPOCH, &1_600_000_000u64.to_ne_bytes())
+        .unwrap();
+    writer
+        .push_raw_tag(
+            tag_ids::FIRMWARE,
+            &StivaleFirmwareTagFlags::BIOS.bits().to_ne_bytes(),
+        )
+        .unwrap();
+
+    let address = writer.struct_address() as usize;
+    unsafe { StivaleStruct::load_with_validation(address) }.unwrap()
+}
+
+#[test]
+fn validation() {
+    let mut buffer = Aligned::<4096>::new();
+    let stivale_struct = fabricate(&mut buffer);
+
+    assert!(stivale_struct.validate_basic());
+    assert!(stivale_struct.is_valid());
+    assert_eq!(stivale_struct.bootloader_info().to_string(), "Limine v2.0");
+
+    let address = stivale_struct as *const StivaleStruct as usize;
+    assert_eq!(
+        unsafe { StivaleStruct::load_with_validation(address + 1) }.unwrap_err(),
+        LoadError::Misaligned
+    );
+}
+
+#[test]
+fn tag_chain() {
+    let mut buffer = Aligned::<4096>::new();
+    let stivale_struct = fabricate(&mut buffer);
+
+    let debug = format!("{:?}", stivale_struct);
+    assert!(
+        debug.contains("[command_line, memory_map, modules, framebuffer, smp, epoch, firmware]")
+    );
+
+    assert!(stivale_struct.terminal().is_none());
+    assert!(stivale_struct.uart().is_none());
+    assert!(stivale_struct.terminal_or_uart().is_none());
+    assert!(stivale_struct.get_tag(tag_ids::RSDP).is_none());
+}
+
+#[test]
+fn fixed_size_tags() {
+    let mut buffer = Aligned::<4096>::new();
+    let stivale_struct = fabricate(&mut buffer);
+
+    let command_line = stivale_struct.command_line().unwrap();
+    let string = unsafe { std::ffi::CStr::from_ptr(command_line.command_line as *const _) };
+    assert_eq!(string.to_str(), Ok("console=ttyS0"));
+
+    assert_eq!(stivale_struct.timestamp(), Some(1_600_000_000));
+    assert_eq!(
+        stivale_struct.firmware().unwrap().flags,
+        StivaleFirmwareTagFlags::BIOS
+    );
+
+    let framebuffer = stivale_struct.require_framebuffer();
+    assert_eq!(framebuffer.framebuffer_addr, 0xfd000000);
+    assert_eq!(framebuffer.size(), 3200 * 600);
+    assert_eq!(framebuffer.encode_rgb(0x11, 0x22, 0x33), 0x112233);
+    assert_eq!(framebuffer.blue_mask().apply(0xff), 0xff);
+}
+
+#[test]
+fn memory_map() {
+    let mut buffer = Aligned::<4096>::new();
+    let memory_map = fabricate(&mut buffer).require_memory_map();
+
+    assert_eq!(memory_map.as_slice().len(), 4);
+    assert_eq!(memory_map.total_memory_bytes(), 0x7a0000);
+    assert_eq!(memory_map.span_bytes(), Some(0x800000));
+    assert_eq!(
+        memory_map.region_at_address(0x1fffff).unwrap().base,
+        0x100000
+    );
+    assert_eq!(
+        memory_map
+            .region_at_address_fast(0x300000)
+            .unwrap()
+            .entry_type(),
+        StivaleMemoryMapEntryType::Usable
+    );
+
+    // Drop the kernel's stack from the high usable region.
+    let usable = memory_map
+        .subtract_regions([(0x300000, 0x10000)].iter().copied())
+        .map(|entry| (entry.base, entry.length))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        usable,
+        [
+            (0x1000, 0x9f000),
+            (0x200000, 0x100000),
+            (0x310000, 0x4f0000)
+        ]
+    );
+}
+
+#[test]
+fn modules() {
+    let mut buffer = Aligned::<4096>::new();
+    let modules = fabricate(&mut buffer).modules().unwrap();
+
+    let names = modules
+        .iter()
+        .map(StivaleModule::as_str)
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["initrd", "font.psf"]);
+    assert_eq!(modules.as_slice()[1].size(), 0x800);
+    assert_eq!(
+        modules.find_by_address(0x500400).map(StivaleModule::as_str),
+        Some("font.psf")
+    );
+}
+
+#[test]
+fn smp() {
+    let mut buffer = Aligned::<4096>::new();
+    let smp = fabricate(&mut buffer).require_smp();
+
+    assert_eq!(smp.cpu_count(), 2);
+    assert_eq!(smp.flags, StivaleSmpHeaderTagFlags::X2APIC);
+    assert_eq!(smp.as_slice()[1].acpi_processor_uid, 1);
+    assert!(format!("{:?}", smp).contains("cpu_count: 2"));
+}
+
+#[test]
+fn header_parse() {
+    static FRAMEBUFFER: StivaleFramebufferHeaderTag =
+        StivaleFramebufferHeaderTag::new().framebuffer_bpp(24);
+
+    let header = StivaleHeader::new()
+        .flags(1 << 1)
+        .tags(&FRAMEBUFFER as *const StivaleFramebufferHeaderTag as *const ());
+
+    let parsed = header::parse(unsafe { as_bytes(&header) }).unwrap();
+    assert_eq!(parsed.flags, 1 << 1);
+    assert_eq!(parsed.entry_point, 0);
+
+    let tag_bytes = unsafe { as_bytes(&FRAMEBUFFER) };
+    let tags = parsed
+        .tags_iter(|address| (address == parsed.tags).then_some(tag_bytes))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(tags.len(), 1);
+    assert_eq!(tags[0].kind(), HeaderTagKind::Framebuffer);
+    assert_eq!(tags[0].data()[4..6], 24u16.to_ne_bytes());
+
+    assert_eq!(unsafe { header.tags_chain_len() }, 1);
+}